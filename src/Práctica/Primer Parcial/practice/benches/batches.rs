@@ -0,0 +1,133 @@
+// Pasa elementos de un productor a un consumidor por el BoundedBuffer y por el
+// ConcurrentCircularBuffer, de a uno y por lotes, y muestra cuánto tarda cada uno. Del
+// BoundedBuffer muestra además cuántas veces alguno de los dos tuvo que esperar. Por lotes se toma
+// el lock y se despierta al otro muchas menos veces.
+// Se corre con `cargo bench --bench batches`.
+//
+// practice es un binario y no se puede usar como dependencia, así que el bench compila los mismos
+// archivos de los buffers. Lo que esos archivos traen y acá no se usa no es un error.
+#[path = "../src/bounded_buffer.rs"]
+#[allow(dead_code, unused_imports)]
+mod bounded_buffer;
+#[path = "../src/circular_buffer.rs"]
+#[allow(dead_code, unused_imports)]
+mod circular_buffer;
+
+use bounded_buffer::{BoundedBuffer, BufferEvent};
+use circular_buffer::ConcurrentCircularBuffer;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TOTAL: i32 = 200_000;
+const CAPACITY: usize = 64;
+const BATCHES: [usize; 4] = [1, 4, 16, 64];
+
+// Lo que usa `transfer` de cada buffer. El consumidor sabe cuántos elementos vienen, así que no
+// hace falta cerrar el buffer.
+trait Buffer: Send + Sync + 'static {
+    fn put(&self, element: i32);
+    fn put_all(&self, elements: Range<i32>);
+    fn take(&self) -> i32;
+    fn take_up_to(&self, n: usize) -> Vec<i32>;
+}
+
+impl Buffer for BoundedBuffer<i32> {
+    fn put(&self, element: i32) {
+        BoundedBuffer::put(self, element).unwrap();
+    }
+
+    fn put_all(&self, elements: Range<i32>) {
+        BoundedBuffer::put_all(self, elements).unwrap();
+    }
+
+    fn take(&self) -> i32 {
+        BoundedBuffer::take(self).unwrap()
+    }
+
+    fn take_up_to(&self, n: usize) -> Vec<i32> {
+        BoundedBuffer::take_up_to(self, n)
+    }
+}
+
+impl Buffer for ConcurrentCircularBuffer<i32> {
+    fn put(&self, element: i32) {
+        self.add(element);
+    }
+
+    fn put_all(&self, elements: Range<i32>) {
+        ConcurrentCircularBuffer::put_all(self, elements);
+    }
+
+    fn take(&self) -> i32 {
+        self.remove()
+    }
+
+    fn take_up_to(&self, n: usize) -> Vec<i32> {
+        ConcurrentCircularBuffer::take_up_to(self, n)
+    }
+}
+
+// Con `batch == 1` usa put/take; si no, put_all/take_up_to
+fn transfer<B: Buffer>(buffer: B, batch: usize) -> Duration {
+    let buffer = Arc::new(buffer);
+    let start = Instant::now();
+    let producer = {
+        let buffer = Arc::clone(&buffer);
+        thread::spawn(move || {
+            if batch == 1 {
+                (0..TOTAL).for_each(|i| buffer.put(i));
+            } else {
+                buffer.put_all(0..TOTAL);
+            }
+        })
+    };
+    let mut sum = 0i64;
+    let mut received = 0;
+    while received < TOTAL as usize {
+        if batch == 1 {
+            sum += buffer.take() as i64;
+            received += 1;
+        } else {
+            let elements = buffer.take_up_to(batch);
+            received += elements.len();
+            sum += elements.into_iter().map(|i| i as i64).sum::<i64>();
+        }
+    }
+    producer.join().unwrap();
+    let elapsed = start.elapsed();
+    assert_eq!(sum, (0..TOTAL as i64).sum::<i64>());
+    elapsed
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>14} {:>10} {:>14}",
+        "lote", "bounded", "esperas", "circular"
+    );
+    for batch in BATCHES {
+        let waits = Arc::new(AtomicUsize::new(0));
+        let bounded = {
+            let waits = Arc::clone(&waits);
+            BoundedBuffer::with_hook(CAPACITY, move |e| {
+                if matches!(
+                    e,
+                    BufferEvent::ProducerWaiting | BufferEvent::ConsumerWaiting
+                ) {
+                    waits.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        };
+        let bounded = transfer(bounded, batch);
+        let circular = transfer(ConcurrentCircularBuffer::new(CAPACITY), batch);
+        println!(
+            "{:>6} {:>14?} {:>10} {:>14?}",
+            batch,
+            bounded,
+            waits.load(Ordering::Relaxed),
+            circular
+        );
+    }
+}
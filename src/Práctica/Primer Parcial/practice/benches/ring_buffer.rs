@@ -0,0 +1,84 @@
+// Compara el ConcurrentCircularBuffer (Mutex + Condvar) contra el RingBuffer sin locks de
+// non-blocking con N productores y N consumidores sobre un buffer chico.
+// Se corre con `cargo bench --bench ring_buffer`.
+//
+// practice es un binario y no se puede usar como dependencia, así que el bench compila el mismo
+// archivo del ConcurrentCircularBuffer. Lo que ese archivo trae y acá no se usa (el otro buffer,
+// los imports de sus tests) no es un error.
+#[path = "../src/circular_buffer.rs"]
+#[allow(dead_code, unused_imports)]
+mod circular_buffer;
+
+use circular_buffer::ConcurrentCircularBuffer;
+use non_blocking::ring_buffer::RingBuffer;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ITEMS_PER_PRODUCER: usize = 20_000;
+const PAIRS: [usize; 5] = [1, 2, 4, 8, 16];
+const CAPACITY: usize = 64;
+
+trait BoundedBuffer: Send + Sync + 'static {
+    fn add(&self, element: usize);
+    fn remove(&self) -> usize;
+}
+
+impl BoundedBuffer for RingBuffer<usize> {
+    fn add(&self, element: usize) {
+        self.push(element);
+    }
+
+    fn remove(&self) -> usize {
+        self.pop()
+    }
+}
+
+impl BoundedBuffer for ConcurrentCircularBuffer<usize> {
+    fn add(&self, element: usize) {
+        ConcurrentCircularBuffer::add(self, element);
+    }
+
+    fn remove(&self) -> usize {
+        ConcurrentCircularBuffer::remove(self)
+    }
+}
+
+fn run<B: BoundedBuffer>(buffer: B, pairs: usize) -> Duration {
+    let buffer = Arc::new(buffer);
+    let barrier = Arc::new(Barrier::new(2 * pairs + 1));
+    let mut handles = Vec::with_capacity(2 * pairs);
+    for p in 0..pairs {
+        let b = Arc::clone(&buffer);
+        let start = Arc::clone(&barrier);
+        handles.push(thread::spawn(move || {
+            start.wait();
+            for i in 0..ITEMS_PER_PRODUCER {
+                b.add(p * ITEMS_PER_PRODUCER + i);
+            }
+        }));
+        let b = Arc::clone(&buffer);
+        let start = Arc::clone(&barrier);
+        handles.push(thread::spawn(move || {
+            start.wait();
+            for _ in 0..ITEMS_PER_PRODUCER {
+                b.remove();
+            }
+        }));
+    }
+    barrier.wait();
+    let t0 = Instant::now();
+    for h in handles {
+        h.join().unwrap();
+    }
+    t0.elapsed()
+}
+
+fn main() {
+    println!("{:>7} {:>14} {:>14}", "pares", "mutex", "lock-free");
+    for pairs in PAIRS {
+        let blocking = run(ConcurrentCircularBuffer::new(CAPACITY), pairs);
+        let lock_free = run(RingBuffer::new(CAPACITY), pairs);
+        println!("{:>7} {:>14?} {:>14?}", pairs, blocking, lock_free);
+    }
+}
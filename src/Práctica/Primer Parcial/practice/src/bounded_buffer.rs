@@ -1,20 +1,51 @@
 // Descripción: Unos hilos producen datos y otros los consumen. El buffer tiene capacidad limitada.
 
+// Todas las operaciones esperan en el Condvar que corresponde y avisan al otro lado al soltar el
+// lock. Después de `close` no entra nada más: los que esperaban para poner fallan, y los que
+// esperaban para sacar se llevan lo que quedaba y después reciben None.
+//
+// Cierre automático: cada `Producer` se registra al crearse y se da de baja al soltarse. Cuando se
+// va el último, el buffer se cierra solo y los consumidores, después de vaciarlo, reciben None.
+// Así no importa cuántos productores y consumidores haya ni cuánto produzca cada uno. Como con
+// `mpsc::Sender`, sólo el primero sale de `Producer::new`, que falla si el buffer ya tuvo
+// productores o está cerrado; los demás se sacan de uno vivo con `clone_with_id`, así la cuenta
+// nunca llega a cero mientras alguien todavía puede agregar otro.
+//
+// Nada de prints ni sleeps acá adentro: el que quiera ver qué pasa registra un hook. El hook corre
+// con el lock tomado, así que tiene que ser corto.
 use std::collections::VecDeque;
-use std::sync::{Arc, Condvar, Mutex};
-use std::thread::sleep;
-use std::time::Duration;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferEvent {
+    ProducerWaiting,
+    ConsumerWaiting,
+    // Con la cantidad de elementos que quedaron
+    Put { len: usize },
+    Taken { len: usize },
+    // Operaciones por lote: cuántos entraron o salieron de una vez
+    PutBatch { count: usize, len: usize },
+    TakenBatch { count: usize, len: usize },
+    Closed,
+}
+
+type Hook = Box<dyn Fn(BufferEvent) + Send + Sync>;
 
 pub struct BoundedBuffer<T> {
     data: Mutex<Data<T>>,
     not_empty: Condvar,
     not_full: Condvar,
+    hook: Option<Hook>,
 }
 
 struct Data<T> {
     buffer: VecDeque<T>,
     capacity: usize,
     size: usize,
+    closed: bool,
+    producers: usize,
 }
 
 impl<T> Data<T> {
@@ -23,16 +54,324 @@ impl<T> Data<T> {
             buffer: VecDeque::with_capacity(capacity),
             capacity,
             size: 0,
+            closed: false,
+            producers: 0,
+        }
+    }
+
+    fn push(&mut self, element: T) {
+        self.buffer.push_back(element);
+        self.size += 1;
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let element = self.buffer.pop_front()?;
+        self.size -= 1;
+        Some(element)
+    }
+}
+
+// El elemento vuelve en el error para que no se pierda
+#[derive(Debug, PartialEq, Eq)]
+pub enum PutError<T> {
+    // Lleno (en `put_timeout`: siguió lleno hasta el plazo)
+    Full(T),
+    Closed(T),
+}
+
+impl<T> fmt::Display for PutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PutError::Full(_) => write!(f, "el buffer está lleno"),
+            PutError::Closed(_) => write!(f, "el buffer está cerrado"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for PutError<T> {}
+
+// Por qué `Producer::new` no pudo dar el primer productor
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProducerError {
+    // Los demás salen de `clone_with_id`
+    HasProducers,
+    Closed,
+}
+
+impl fmt::Display for ProducerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProducerError::HasProducers => write!(f, "el buffer ya tiene productores"),
+            ProducerError::Closed => write!(f, "el buffer está cerrado"),
         }
     }
 }
 
+impl std::error::Error for ProducerError {}
+
 impl<T> BoundedBuffer<T> {
     pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "la capacidad tiene que ser positiva");
         BoundedBuffer {
             data: Mutex::new(Data::new(capacity)),
             not_empty: Condvar::new(),
             not_full: Condvar::new(),
+            hook: None,
+        }
+    }
+
+    pub fn with_hook<F>(capacity: usize, hook: F) -> Self
+    where
+        F: Fn(BufferEvent) + Send + Sync + 'static,
+    {
+        BoundedBuffer {
+            hook: Some(Box::new(hook)),
+            ..Self::new(capacity)
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    // Espera mientras esté lleno. Falla sólo si está cerrado.
+    pub fn put(&self, element: T) -> Result<(), T> {
+        match self.put_until(element, None) {
+            Ok(()) => Ok(()),
+            Err(PutError::Closed(element)) => Err(element),
+            Err(PutError::Full(_)) => unreachable!("sin plazo no hay timeout"),
+        }
+    }
+
+    pub fn try_put(&self, element: T) -> Result<(), PutError<T>> {
+        let data = self.lock();
+        if data.closed {
+            return Err(PutError::Closed(element));
+        }
+        if data.size == data.capacity {
+            return Err(PutError::Full(element));
+        }
+        self.push(data, element);
+        Ok(())
+    }
+
+    pub fn put_timeout(&self, element: T, timeout: Duration) -> Result<(), PutError<T>> {
+        self.put_until(element, Some(Instant::now() + timeout))
+    }
+
+    // Espera mientras esté vacío. None cuando está cerrado y ya no queda nada.
+    pub fn take(&self) -> Option<T> {
+        self.take_until(None)
+    }
+
+    pub fn try_take(&self) -> Option<T> {
+        let data = self.lock();
+        self.pop(data)
+    }
+
+    // None también si se vence el plazo: `is_closed` dice cuál de los dos fue
+    pub fn take_timeout(&self, timeout: Duration) -> Option<T> {
+        self.take_until(Some(Instant::now() + timeout))
+    }
+
+    // Mete todo, esperando cuando se llena. Cada vez que consigue el lock pone todo lo que entra y
+    // avisa, así los consumidores pueden ir sacando lo primero mientras espera lugar para el resto.
+    // Si el buffer se cierra en el medio, devuelve lo que no llegó a entrar.
+    pub fn put_all<I: IntoIterator<Item = T>>(&self, items: I) -> Result<(), Vec<T>> {
+        let mut items = items.into_iter().peekable();
+        let mut data = self.lock();
+        while items.peek().is_some() {
+            while data.size == data.capacity && !data.closed {
+                self.trace(BufferEvent::ProducerWaiting);
+                data = self.not_full.wait(data).unwrap();
+            }
+            if data.closed {
+                return Err(items.collect());
+            }
+            let mut count = 0;
+            while data.size < data.capacity {
+                let Some(element) = items.next() else { break };
+                data.push(element);
+                count += 1;
+            }
+            self.trace(BufferEvent::PutBatch {
+                count,
+                len: data.size,
+            });
+            notify(&self.not_empty, count);
+        }
+        Ok(())
+    }
+
+    // Espera a que haya algo y se lleva hasta `n` de una vez, aunque sean menos. Vacío sólo cuando
+    // está cerrado y no queda nada.
+    pub fn take_up_to(&self, n: usize) -> Vec<T> {
+        assert!(n > 0, "hay que pedir al menos uno");
+        let mut data = self.lock();
+        while data.size == 0 && !data.closed {
+            self.trace(BufferEvent::ConsumerWaiting);
+            data = self.not_empty.wait(data).unwrap();
+        }
+        self.pop_many(data, n)
+    }
+
+    // Todo lo que hay en este momento, sin esperar
+    pub fn drain_available(&self) -> Vec<T> {
+        let data = self.lock();
+        self.pop_many(data, usize::MAX)
+    }
+
+    // Despierta a todos los que están esperando
+    pub fn close(&self) {
+        let mut data = self.lock();
+        if data.closed {
+            return;
+        }
+        data.closed = true;
+        self.trace(BufferEvent::Closed);
+        drop(data);
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    // Sólo desde `Producer`, que se registra una vez y se da de baja una vez
+    fn register_first_producer(&self) -> Result<(), ProducerError> {
+        let mut data = self.lock();
+        if data.closed {
+            return Err(ProducerError::Closed);
+        }
+        if data.producers > 0 {
+            return Err(ProducerError::HasProducers);
+        }
+        data.producers = 1;
+        Ok(())
+    }
+
+    // Lo registra uno vivo, así que el buffer no se pudo cerrar solo
+    fn register_producer(&self) {
+        self.lock().producers += 1;
+    }
+
+    // Si era el último, cierra el buffer
+    fn deregister_producer(&self) {
+        let mut data = self.lock();
+        data.producers -= 1;
+        let last = data.producers == 0;
+        drop(data);
+        if last {
+            self.close();
+        }
+    }
+
+    pub fn producers(&self) -> usize {
+        self.lock().producers
+    }
+
+    fn put_until(&self, element: T, deadline: Option<Instant>) -> Result<(), PutError<T>> {
+        let mut data = self.lock();
+        while data.size == data.capacity && !data.closed {
+            if expired(deadline) {
+                return Err(PutError::Full(element));
+            }
+            self.trace(BufferEvent::ProducerWaiting);
+            data = wait(&self.not_full, data, deadline);
+        }
+        if data.closed {
+            return Err(PutError::Closed(element));
+        }
+        self.push(data, element);
+        Ok(())
+    }
+
+    fn take_until(&self, deadline: Option<Instant>) -> Option<T> {
+        let mut data = self.lock();
+        while data.size == 0 && !data.closed {
+            if expired(deadline) {
+                return None;
+            }
+            self.trace(BufferEvent::ConsumerWaiting);
+            data = wait(&self.not_empty, data, deadline);
+        }
+        self.pop(data)
+    }
+
+    fn push(&self, mut data: MutexGuard<'_, Data<T>>, element: T) {
+        data.push(element);
+        self.trace(BufferEvent::Put { len: data.size });
+        drop(data);
+        self.not_empty.notify_one();
+    }
+
+    fn pop(&self, mut data: MutexGuard<'_, Data<T>>) -> Option<T> {
+        let element = data.pop()?;
+        self.trace(BufferEvent::Taken { len: data.size });
+        drop(data);
+        self.not_full.notify_one();
+        Some(element)
+    }
+
+    fn pop_many(&self, mut data: MutexGuard<'_, Data<T>>, n: usize) -> Vec<T> {
+        let count = n.min(data.size);
+        let elements: Vec<T> = (0..count).filter_map(|_| data.pop()).collect();
+        if count > 0 {
+            self.trace(BufferEvent::TakenBatch {
+                count,
+                len: data.size,
+            });
+        }
+        drop(data);
+        notify(&self.not_full, count);
+        elements
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Data<T>> {
+        self.data.lock().unwrap()
+    }
+
+    fn trace(&self, event: BufferEvent) {
+        if let Some(hook) = &self.hook {
+            hook(event);
+        }
+    }
+}
+
+// Con varios elementos nuevos (o lugares libres) puede seguir más de uno
+fn notify(condvar: &Condvar, count: usize) {
+    match count {
+        0 => {}
+        1 => condvar.notify_one(),
+        _ => condvar.notify_all(),
+    }
+}
+
+fn expired(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+// Vuelve con un aviso o al vencer el plazo. El que llama mira primero la condición y después el
+// plazo, así no da por vencida una espera en la que justo llegó lo que esperaba.
+fn wait<'a, T>(
+    condvar: &Condvar,
+    data: MutexGuard<'a, Data<T>>,
+    deadline: Option<Instant>,
+) -> MutexGuard<'a, Data<T>> {
+    match deadline {
+        None => condvar.wait(data).unwrap(),
+        Some(deadline) => {
+            let left = deadline.saturating_duration_since(Instant::now());
+            condvar.wait_timeout(data, left).unwrap().0
         }
     }
 }
@@ -49,22 +388,15 @@ impl<T> Consumer<T> {
             consumer_id,
         }
     }
-    pub fn consume(&self) -> T {
-        let mut data = self.buffer_ref.data.lock().unwrap();
-        while data.size == 0 {
-            println!(
-                "Consumer with id {} is waiting, buffer is empty!",
-                self.consumer_id
-            );
-            data = self.buffer_ref.not_empty.wait(data).unwrap();
-        }
-        let element = data.buffer.pop_front();
-        data.size -= 1;
-        drop(data);
-        self.buffer_ref.not_full.notify_all();
-        sleep(Duration::from_millis(500));
-        println!("Consumer with id {} is consuming!", self.consumer_id);
-        element.unwrap()
+    pub fn id(&self) -> i16 {
+        self.consumer_id
+    }
+    // None cuando ya no hay productores y no queda nada
+    pub fn consume(&self) -> Option<T> {
+        self.buffer_ref.take()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(|| self.consume())
     }
 }
 
@@ -74,29 +406,287 @@ pub struct Producer<T> {
 }
 
 impl<T> Producer<T> {
-    pub fn new(buffer_ref: Arc<BoundedBuffer<T>>, producer_id: i16) -> Self {
-        Producer {
+    // El primer productor de un buffer nuevo
+    pub fn new(buffer_ref: Arc<BoundedBuffer<T>>, producer_id: i16) -> Result<Self, ProducerError> {
+        buffer_ref.register_first_producer()?;
+        Ok(Producer {
             buffer_ref,
             producer_id,
+        })
+    }
+    // Otro productor del mismo buffer
+    pub fn clone_with_id(&self, producer_id: i16) -> Self {
+        self.buffer_ref.register_producer();
+        Producer {
+            buffer_ref: Arc::clone(&self.buffer_ref),
+            producer_id,
         }
     }
-    pub fn produce(&self, element: T) {
-        let mut data = self.buffer_ref.data.lock().unwrap();
-        while data.capacity == data.size {
-            println!(
-                "Producer with id {} is waiting, buffer is full!",
-                self.producer_id
-            );
-            data = self.buffer_ref.not_full.wait(data).unwrap();
+    pub fn id(&self) -> i16 {
+        self.producer_id
+    }
+    pub fn produce(&self, element: T) -> Result<(), T> {
+        self.buffer_ref.put(element)
+    }
+}
+
+impl<T> Drop for Producer<T> {
+    fn drop(&mut self) {
+        self.buffer_ref.deregister_producer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // 1. Sale en el mismo orden en que entró
+    #[test]
+    fn put_and_take_are_fifo() {
+        let buffer = BoundedBuffer::new(3);
+        for i in 0..3 {
+            buffer.put(i).unwrap();
         }
-        println!("Producer with id {} is producing!", self.producer_id);
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(
+            (0..3).map(|_| buffer.take().unwrap()).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert!(buffer.is_empty());
+    }
 
-        data.buffer.push_back(element);
-        data.size += 1;
-        sleep(Duration::from_millis(500));
-        drop(data);
+    // 2. Las variantes try no esperan, las de timeout se rinden al vencer el plazo
+    #[test]
+    fn try_and_timeout_variants() {
+        let buffer = BoundedBuffer::new(1);
+        assert_eq!(buffer.try_take(), None);
+        assert_eq!(buffer.take_timeout(Duration::from_millis(20)), None);
+        buffer.try_put(1).unwrap();
+        assert_eq!(buffer.try_put(2), Err(PutError::Full(2)));
+        let t0 = Instant::now();
+        assert_eq!(
+            buffer.put_timeout(3, Duration::from_millis(20)),
+            Err(PutError::Full(3))
+        );
+        assert!(t0.elapsed() >= Duration::from_millis(20));
+        assert_eq!(buffer.try_take(), Some(1));
+    }
+
+    // 3. Un productor bloqueado sigue apenas alguien saca
+    #[test]
+    fn blocked_put_resumes_after_take() {
+        let buffer = Arc::new(BoundedBuffer::new(1));
+        buffer.put(1).unwrap();
+        let producer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || buffer.put(2))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!producer.is_finished());
+        assert_eq!(buffer.take(), Some(1));
+        producer.join().unwrap().unwrap();
+        assert_eq!(buffer.take(), Some(2));
+    }
+
+    // 4. close despierta a los consumidores bloqueados con None y a los productores con error
+    #[test]
+    fn close_wakes_everyone() {
+        let empty = Arc::new(BoundedBuffer::<i32>::new(1));
+        let consumers: Vec<_> = (0..3)
+            .map(|_| {
+                let buffer = Arc::clone(&empty);
+                thread::spawn(move || buffer.take())
+            })
+            .collect();
+        let full = Arc::new(BoundedBuffer::new(1));
+        full.put(0).unwrap();
+        let producer = {
+            let buffer = Arc::clone(&full);
+            thread::spawn(move || buffer.put(1))
+        };
+        thread::sleep(Duration::from_millis(50));
+        empty.close();
+        full.close();
+        for c in consumers {
+            assert_eq!(c.join().unwrap(), None);
+        }
+        assert_eq!(producer.join().unwrap(), Err(1));
+    }
+
+    // 5. Cerrado no entra nada, pero lo que ya estaba se puede sacar
+    #[test]
+    fn close_drains_remaining_items() {
+        let buffer = BoundedBuffer::new(4);
+        buffer.put(1).unwrap();
+        buffer.put(2).unwrap();
+        buffer.close();
+        assert!(buffer.is_closed());
+        assert_eq!(buffer.put(3), Err(3));
+        assert_eq!(buffer.try_put(3), Err(PutError::Closed(3)));
+        assert_eq!(buffer.take(), Some(1));
+        assert_eq!(buffer.take_timeout(Duration::from_secs(1)), Some(2));
+        assert_eq!(buffer.take(), None);
+    }
+
+    // Reparte `per_producer` elementos por productor y devuelve lo que recibió cada consumidor
+    fn run(producers: i16, consumers: i16, per_producer: i32) -> Vec<Vec<i32>> {
+        let buffer = Arc::new(BoundedBuffer::new(3));
+        let first = Producer::new(Arc::clone(&buffer), 0).unwrap();
+        let mut producers: Vec<_> = (1..producers).map(|id| first.clone_with_id(id)).collect();
+        producers.push(first);
+        let consumers: Vec<_> = (0..consumers)
+            .map(|id| {
+                let consumer = Consumer::new(Arc::clone(&buffer), id);
+                thread::spawn(move || consumer.iter().collect::<Vec<_>>())
+            })
+            .collect();
+        let producers: Vec<_> = producers
+            .into_iter()
+            .map(|producer| {
+                thread::spawn(move || {
+                    for i in 0..per_producer {
+                        producer.produce(producer.id() as i32 * 1000 + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for p in producers {
+            p.join().unwrap();
+        }
+        consumers.into_iter().map(|c| c.join().unwrap()).collect()
+    }
+
+    fn expected(producers: i32, per_producer: i32) -> Vec<i32> {
+        (0..producers)
+            .flat_map(|p| (0..per_producer).map(move |i| p * 1000 + i))
+            .collect()
+    }
+
+    // 6. Pocos productores y muchos consumidores: todos terminan y nada se pierde
+    #[test]
+    fn more_consumers_than_producers() {
+        let received = run(2, 5, 50);
+        let mut all: Vec<_> = received.into_iter().flatten().collect();
+        all.sort_unstable();
+        assert_eq!(all, expected(2, 50));
+    }
+
+    // 7. Muchos productores y pocos consumidores
+    #[test]
+    fn more_producers_than_consumers() {
+        let received = run(7, 2, 3);
+        let mut all: Vec<_> = received.into_iter().flatten().collect();
+        all.sort_unstable();
+        assert_eq!(all, expected(7, 3));
+    }
+
+    // 8. El buffer se cierra recién cuando se va el último productor
+    #[test]
+    fn closes_when_last_producer_leaves() {
+        let buffer = Arc::new(BoundedBuffer::new(2));
+        let first = Producer::new(Arc::clone(&buffer), 0).unwrap();
+        let second = first.clone_with_id(1);
+        assert_eq!(buffer.producers(), 2);
+        first.produce(1).unwrap();
+        drop(first);
+        assert!(!buffer.is_closed());
+        second.produce(2).unwrap();
+        drop(second);
+        assert!(buffer.is_closed());
+        let consumer = Consumer::new(buffer, 0);
+        assert_eq!(consumer.iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(consumer.consume(), None);
+    }
+
+    // 9. put_all avanza de a tandas: lo que entra, entra, y el resto espera lugar
+    #[test]
+    fn put_all_makes_partial_progress() {
+        let buffer = Arc::new(BoundedBuffer::new(4));
+        let producer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || buffer.put_all(0..10))
+        };
+        let mut received = Vec::new();
+        while received.len() < 10 {
+            let batch = buffer.take_up_to(3);
+            assert!(!batch.is_empty() && batch.len() <= 3);
+            received.extend(batch);
+        }
+        producer.join().unwrap().unwrap();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+    }
+
+    // 10. take_up_to se lleva lo que haya aunque sea menos que n; drain_available no espera
+    #[test]
+    fn take_up_to_and_drain_available() {
+        let buffer = BoundedBuffer::new(8);
+        assert!(buffer.drain_available().is_empty());
+        buffer.put_all([1, 2, 3]).unwrap();
+        assert_eq!(buffer.take_up_to(2), vec![1, 2]);
+        assert_eq!(buffer.take_up_to(5), vec![3]);
+        buffer.put_all([4, 5]).unwrap();
+        assert_eq!(buffer.drain_available(), vec![4, 5]);
+        buffer.close();
+        assert!(buffer.take_up_to(5).is_empty());
+    }
+
+    // 11. Si se cierra mientras put_all espera lugar, devuelve lo que no entró
+    #[test]
+    fn put_all_returns_leftovers_when_closed() {
+        let buffer = Arc::new(BoundedBuffer::new(2));
+        let producer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || buffer.put_all(0..5))
+        };
+        thread::sleep(Duration::from_millis(50));
+        buffer.close();
+        assert_eq!(producer.join().unwrap(), Err(vec![2, 3, 4]));
+        assert_eq!(buffer.drain_available(), vec![0, 1]);
+    }
+
+    // 12. El hook ve cada evento
+    #[test]
+    fn hook_traces_events() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let buffer = {
+            let events = Arc::clone(&events);
+            BoundedBuffer::with_hook(1, move |e| events.lock().unwrap().push(e))
+        };
+        buffer.put(1).unwrap();
+        assert!(buffer.put_timeout(2, Duration::from_millis(10)).is_err());
+        buffer.take().unwrap();
+        buffer.close();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                BufferEvent::Put { len: 1 },
+                BufferEvent::ProducerWaiting,
+                BufferEvent::Taken { len: 0 },
+                BufferEvent::Closed
+            ]
+        );
+    }
+
+    // 13. Un productor suelto no puede sumarse a uno vivo ni llegar tarde a un buffer cerrado
+    #[test]
+    fn late_producer_must_be_cloned() {
+        let buffer = Arc::new(BoundedBuffer::<i32>::new(1));
+        let first = Producer::new(Arc::clone(&buffer), 0).unwrap();
+        assert_eq!(
+            Producer::new(Arc::clone(&buffer), 1).err(),
+            Some(ProducerError::HasProducers)
+        );
+        assert_eq!(buffer.producers(), 1);
+        drop(first);
+        assert!(buffer.is_closed());
+        assert_eq!(Producer::new(buffer, 1).err(), Some(ProducerError::Closed));
 
-        self.buffer_ref.not_empty.notify_all();
-        sleep(Duration::from_millis(500));
+        let closed_by_hand = Arc::new(BoundedBuffer::<i32>::new(1));
+        closed_by_hand.close();
+        assert_eq!(
+            Producer::new(closed_by_hand, 0).err(),
+            Some(ProducerError::Closed)
+        );
     }
 }
@@ -0,0 +1,335 @@
+/*
+4. **Broker Pub/Sub manual**
+   - **Descripción:** Implementa un broker que recibe mensajes de distintos “publicadores” y, según el tópico, los reenvía a uno o más “suscriptores”. Usa canales internos para el enrutamiento.
+   - **Retos conceptuales:**
+     - Mantener un mapa tópico → `Sender` múltiple.
+     - Detectar suscriptores que cancelan (cerrado de canal).
+ */
+
+// Un único hilo de ruteo es dueño del mapa, así que no hace falta ningún lock: publicadores y
+// suscripciones le llegan como comandos por un mismo canal.
+//
+// Los tópicos son niveles separados por `/`. En un patrón de suscripción `*` reemplaza un nivel
+// y `#` (sólo al final) cualquier cantidad de niveles, incluso ninguno:
+// `sensores/*/temp` recibe `sensores/cocina/temp`, `sensores/#` recibe todo lo de sensores.
+//
+// Las bajas no van por el canal de comandos: si cada `Subscription` tuviera un emisor, el hilo de
+// ruteo no terminaría mientras quede alguna viva. Al soltarse anotan su id en `departed`, y el
+// hilo las borra antes de atender el próximo comando, le toque o no al suscriptor. Una baja puede
+// anotarse antes de que se atienda su `Subscribe`: esa queda guardada y el alta se descarta.
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+enum Command<M> {
+    Publish {
+        topic: String,
+        message: M,
+    },
+    Subscribe {
+        pattern: String,
+        id: u64,
+        subscriber: Sender<(String, M)>,
+    },
+    SubscriberCount {
+        reply: Sender<usize>,
+    },
+}
+
+pub struct Broker<M> {
+    publisher: Publisher<M>,
+    next_id: AtomicU64,
+    departed: Arc<Mutex<Vec<u64>>>,
+}
+
+// Se puede clonar y mandar a cualquier hilo
+pub struct Publisher<M> {
+    commands: Sender<Command<M>>,
+}
+
+impl<M> Clone for Publisher<M> {
+    fn clone(&self) -> Self {
+        Publisher {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+// Se lee como el `Receiver` que envuelve. Al soltarla se da de baja.
+pub struct Subscription<M> {
+    id: u64,
+    receiver: Receiver<(String, M)>,
+    departed: Arc<Mutex<Vec<u64>>>,
+}
+
+impl<M> Deref for Subscription<M> {
+    type Target = Receiver<(String, M)>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.receiver
+    }
+}
+
+impl<M> Drop for Subscription<M> {
+    fn drop(&mut self) {
+        self.departed.lock().unwrap().push(self.id);
+    }
+}
+
+impl<M: Clone + Send + 'static> Broker<M> {
+    // El hilo de ruteo termina cuando se sueltan el broker y todos sus publicadores; ahí se
+    // sueltan también los emisores de los suscriptores y sus `recv` devuelven error
+    pub fn new() -> Self {
+        let (commands, inbox) = channel();
+        let departed = Arc::new(Mutex::new(Vec::new()));
+        let route_departed = Arc::clone(&departed);
+        thread::spawn(move || route(inbox, route_departed));
+        Broker {
+            publisher: Publisher { commands },
+            next_id: AtomicU64::new(0),
+            departed,
+        }
+    }
+
+    pub fn publisher(&self) -> Publisher<M> {
+        self.publisher.clone()
+    }
+
+    // Cada mensaje llega junto con el tópico con el que se publicó
+    pub fn subscribe(&self, pattern: &str) -> Subscription<M> {
+        let (subscriber, receiver) = channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.publisher.send(Command::Subscribe {
+            pattern: pattern.to_string(),
+            id,
+            subscriber,
+        });
+        Subscription {
+            id,
+            receiver,
+            departed: Arc::clone(&self.departed),
+        }
+    }
+
+    pub fn publish(&self, topic: &str, message: M) {
+        self.publisher.publish(topic, message);
+    }
+
+    // Suscriptores vivos: las bajas anteriores a esta llamada ya no cuentan
+    pub fn subscriber_count(&self) -> usize {
+        let (reply, answer) = channel();
+        self.publisher.send(Command::SubscriberCount { reply });
+        answer.recv().expect("el hilo de ruteo terminó")
+    }
+}
+
+impl<M: Clone + Send + 'static> Default for Broker<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Publisher<M> {
+    pub fn publish(&self, topic: &str, message: M) {
+        self.send(Command::Publish {
+            topic: topic.to_string(),
+            message,
+        });
+    }
+
+    fn send(&self, command: Command<M>) {
+        self.commands
+            .send(command)
+            .expect("el hilo de ruteo terminó");
+    }
+}
+
+type Subscribers<M> = HashMap<String, Vec<(u64, Sender<(String, M)>)>>;
+
+fn route<M: Clone>(inbox: Receiver<Command<M>>, departed: Arc<Mutex<Vec<u64>>>) {
+    let mut subscribers: Subscribers<M> = HashMap::new();
+    // Bajas que todavía no encontraron a su suscriptor porque su `Subscribe` sigue en la cola
+    let mut gone = HashSet::new();
+    for command in inbox {
+        gone.extend(mem::take(&mut *departed.lock().unwrap()));
+        if !gone.is_empty() {
+            for senders in subscribers.values_mut() {
+                senders.retain(|(id, _)| !gone.remove(id));
+            }
+            subscribers.retain(|_, senders| !senders.is_empty());
+        }
+        match command {
+            Command::Subscribe {
+                pattern,
+                id,
+                subscriber,
+            } => {
+                if !gone.remove(&id) {
+                    subscribers
+                        .entry(pattern)
+                        .or_default()
+                        .push((id, subscriber));
+                }
+            }
+            Command::Publish { topic, message } => {
+                for (pattern, senders) in subscribers.iter_mut() {
+                    if matches(pattern, &topic) {
+                        // Por si la baja todavía no llegó a `departed`
+                        senders.retain(|(_, s)| s.send((topic.clone(), message.clone())).is_ok());
+                    }
+                }
+                subscribers.retain(|_, senders| !senders.is_empty());
+            }
+            Command::SubscriberCount { reply } => {
+                let _ = reply.send(subscribers.values().map(Vec::len).sum());
+            }
+        }
+    }
+}
+
+fn matches(pattern: &str, topic: &str) -> bool {
+    let mut pattern = pattern.split('/');
+    let mut topic = topic.split('/');
+    loop {
+        match (pattern.next(), topic.next()) {
+            (Some("#"), _) => return pattern.next().is_none(),
+            (Some("*"), Some(_)) => {}
+            (Some(p), Some(t)) if p == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    // 1. Comodines
+    #[test]
+    fn wildcard_matching() {
+        assert!(matches("a/b/c", "a/b/c"));
+        assert!(!matches("a/b/c", "a/b"));
+        assert!(!matches("a/b", "a/b/c"));
+        assert!(matches("a/*/c", "a/x/c"));
+        assert!(!matches("a/*/c", "a/x/y/c"));
+        assert!(matches("a/#", "a/x/y/c"));
+        assert!(matches("a/#", "a"));
+        assert!(matches("#", "cualquier/cosa"));
+        assert!(!matches("a/#/c", "a/b/c"));
+    }
+
+    // 2. Un mensaje llega a todos los suscriptores cuyo patrón coincide, y a ningún otro
+    #[test]
+    fn fan_out_to_matching_subscribers() {
+        let broker = Broker::new();
+        let exact = broker.subscribe("sensores/cocina/temp");
+        let any_room = broker.subscribe("sensores/*/temp");
+        let everything = broker.subscribe("sensores/#");
+        let other = broker.subscribe("alarmas/#");
+
+        broker.publish("sensores/cocina/temp", 21);
+        broker.publish("sensores/living/humedad", 60);
+
+        let message = ("sensores/cocina/temp".to_string(), 21);
+        assert_eq!(exact.recv_timeout(TIMEOUT), Ok(message.clone()));
+        assert_eq!(any_room.recv_timeout(TIMEOUT), Ok(message.clone()));
+        assert_eq!(everything.recv_timeout(TIMEOUT), Ok(message));
+        assert_eq!(
+            everything.recv_timeout(TIMEOUT),
+            Ok(("sensores/living/humedad".to_string(), 60))
+        );
+        // Cuando el broker contestó, ya procesó los dos publish anteriores
+        assert_eq!(broker.subscriber_count(), 4);
+        assert!(exact.try_recv().is_err());
+        assert!(any_room.try_recv().is_err());
+        assert!(other.try_recv().is_err());
+    }
+
+    // 3. Publicadores en varios hilos: cada suscriptor recibe todo, en el orden de cada uno
+    #[test]
+    fn publishers_on_many_threads() {
+        let broker = Broker::new();
+        let subscriber = broker.subscribe("numeros/*");
+        let handles: Vec<_> = (0..4)
+            .map(|p| {
+                let publisher = broker.publisher();
+                thread::spawn(move || {
+                    for i in 0..100 {
+                        publisher.publish(&format!("numeros/{p}"), i);
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        let mut per_topic: HashMap<String, Vec<i32>> = HashMap::new();
+        for _ in 0..400 {
+            let (topic, i) = subscriber.recv_timeout(TIMEOUT).unwrap();
+            per_topic.entry(topic).or_default().push(i);
+        }
+        assert_eq!(per_topic.len(), 4);
+        assert!(
+            per_topic
+                .values()
+                .all(|v| *v == (0..100).collect::<Vec<_>>())
+        );
+    }
+
+    // 4. Un suscriptor que se suelta se borra aunque nunca se publique en su tópico
+    #[test]
+    fn dropped_subscribers_are_removed() {
+        let broker = Broker::new();
+        let stays = broker.subscribe("noticias");
+        let leaves = broker.subscribe("noticias");
+        let also_leaves = broker.subscribe("deportes");
+        assert_eq!(broker.subscriber_count(), 3);
+
+        drop(leaves);
+        assert_eq!(broker.subscriber_count(), 2);
+        drop(also_leaves);
+        assert_eq!(broker.subscriber_count(), 1);
+        broker.publish("noticias", "hola");
+        assert_eq!(
+            stays.recv_timeout(TIMEOUT),
+            Ok(("noticias".to_string(), "hola"))
+        );
+    }
+
+    // 5. Una suscripción que se suelta antes de que el broker atienda el alta no queda contada
+    #[test]
+    fn dropped_before_subscribe_is_processed() {
+        let broker = Broker::<i32>::new();
+        drop(broker.subscribe("noticias"));
+        assert_eq!(broker.subscriber_count(), 0);
+        let stays = broker.subscribe("noticias");
+        drop(broker.subscribe("noticias"));
+        assert_eq!(broker.subscriber_count(), 1);
+        drop(stays);
+        assert_eq!(broker.subscriber_count(), 0);
+    }
+
+    // 6. Sin broker ni publicadores, los suscriptores ven el canal cerrado
+    #[test]
+    fn subscribers_see_the_broker_go_away() {
+        let broker = Broker::<i32>::new();
+        let subscriber = broker.subscribe("#");
+        let publisher = broker.publisher();
+        drop(broker);
+        publisher.publish("todavia/anda", 1);
+        drop(publisher);
+        assert_eq!(
+            subscriber.recv_timeout(TIMEOUT),
+            Ok(("todavia/anda".to_string(), 1))
+        );
+        assert!(subscriber.recv_timeout(TIMEOUT).is_err());
+    }
+}
@@ -1,41 +1,3 @@
-/*
-3. **Pool de trabajadores (Worker Pool)**
-   - **Descripción:** Un hilo “dispatcher” envía tareas por un canal a un grupo de K hilos trabajadores que las reciben, las ejecutan y, opcionalmente, responden por otro canal de resultados.
-   - **Retos conceptuales:**
-     - Balanceo de carga entre trabajadores.
-     - Manejo de respuestas por un canal distinto sin bloquear al dispatcher.
-
-4. **Broker Pub/Sub manual**
-   - **Descripción:** Implementa un broker que recibe mensajes de distintos “publicadores” y, según el tópico, los reenvía a uno o más “suscriptores”. Usa canales internos para el enrutamiento.
-   - **Retos conceptuales:**
-     - Mantener un mapa tópico → `Sender` múltiple.
-     - Detectar suscriptores que cancelan (cerrado de canal).
-
-5. **Rate Limiter por tokens**
-   - **Descripción:** Un hilo generador añade “tokens” a intervalos regulares a un canal, y los consumidores deben tomar un token antes de realizar su operación.
-   - **Retos conceptuales:**
-     - Controlar la frecuencia de producción de tokens.
-     - Evitar que los consumidores se bloqueen indefinidamente si no hay tokens.
-
-6. **Handshake (Rendezvous) de dos actores**
-   - **Descripción:** Dos hilos A y B deben sincronizarse en un punto: A envía un mensaje por un canal y espera recibir otro de vuelta, y viceversa.
-   - **Retos conceptuales:**
-     - Evitar deadlocks en la espera mutua.
-     - Decidir orden de envío/recepción.
-
-7. **Monitor de salud (Heartbeat)**
-   - **Descripción:** Varios hilos trabajadores envían periódicamente un “latido” (por ejemplo, su ID y timestamp) a un hilo monitor que los supervisa. Si no recibe el latido de alguno en un plazo, lo considera caído.
-   - **Retos conceptuales:**
-     - Diferenciar mensajes de distintos trabajadores.
-     - Implementar timeouts sin bloquear el canal.
-
-8. **Tareas con prioridades (colas separadas)**
-   - **Descripción:** Dispones de dos canales (alta y baja prioridad). Un “dispatcher” lee primero de la cola alta y, si está vacía, de la baja. Los productores escogen en qué canal enviar según urgencia.
-   - **Retos conceptuales:**
-     - Implementar la selección no bloqueante de múltiples canales (p. ej., comprobando `try_recv`).
-     - Prevenir starvation de la cola baja.
- */
-
 /*
 1. **Productor–Consumidor básico**
    - **Descripción:** Varios hilos productores generan datos (por ejemplo, enteros o mensajes) y los envían por un `Sender<T>` a un único hilo consumidor que los procesa.
@@ -43,8 +5,16 @@
      - Clonar el `Sender` para cada productor.
      - Detectar fin de producción (cerrar el canal).
  */
-use std::sync::mpsc::{Receiver, Sender, channel};
-use std::thread;
+use non_blocking::spsc;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, channel, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 pub fn basic() {
     let (sender, receiver) = channel::<String>();
@@ -93,89 +63,867 @@ fn add_ten(x: i32) -> i32 {
 
 pub fn pipeline() {
     let functions: Vec<NodeFunction<i32>> = vec![add_one, times_two, square, neg, add_ten];
-    let pipeline = Pipeline::new(functions);
-
-    let value = pipeline.run(10);
+    // El tipo de enlace se elige con la anotación; por defecto es `mpsc`
+    let pipeline: Pipeline<i32> = Pipeline::new(functions.clone());
+    let value = pipeline.run(10).unwrap();
     println!("Pipeline final value: {}", value);
 
+    let pipeline: Pipeline<i32, i32, SpscLink> = Pipeline::new(functions);
+    let value = pipeline.run(10).unwrap();
+    println!("Pipeline final value (SPSC): {}", value);
+
+    let pipeline = Pipeline::source::<&str>()
+        .try_stage(|s| s.parse::<i32>())
+        .stage(add_one)
+        .stage(|x| format!("{x} es {}", if x % 2 == 0 { "par" } else { "impar" }))
+        .sink();
+    match pipeline.run("41") {
+        Ok(value) => println!("Pipeline final value (typed): {}", value),
+        Err(error) => println!("Pipeline failed: {}", error),
+    }
+
+    let mut stream = Pipeline::source::<i32>()
+        .stage_parallel(4, true, square)
+        .stage(add_ten)
+        .sink()
+        .run_stream(0..20);
+    let values: Vec<_> = stream.by_ref().map(Result::unwrap).collect();
+    println!("Pipeline stream values: {:?}", values);
+    for metrics in stream.metrics() {
+        println!("{metrics}");
+    }
+}
+
+// Cómo se conectan dos etapas. Cada enlace tiene exactamente un emisor y un receptor, así que
+// además de `mpsc` sirve el canal SPSC de `non_blocking`.
+pub trait Link {
+    type Sender<T: Send>: Send;
+    type Receiver<T: Send>: Send;
+
+    fn link<T: Send>() -> (Self::Sender<T>, Self::Receiver<T>);
+    // Devuelve el valor si del otro lado ya no hay nadie
+    fn send<T: Send>(sender: &Self::Sender<T>, value: T) -> Result<(), T>;
+    // None cuando el emisor se fue y no quedan datos
+    fn recv<T: Send>(receiver: &Self::Receiver<T>) -> Option<T>;
+}
+
+// Capacidad de cada enlace mpsc. Con límite, una etapa atrasada frena a las anteriores y al hilo
+// que alimenta el pipeline, en lugar de que se acumulen las entradas (con `run_stream(0..)`, sin
+// fin).
+const MPSC_CAPACITY: usize = 64;
+
+// Cuántas dead letters se guardan sin que nadie las lea; las que vengan después se descartan
+const DEAD_LETTER_CAPACITY: usize = 1024;
+
+pub struct MpscLink;
+
+impl Link for MpscLink {
+    type Sender<T: Send> = SyncSender<T>;
+    type Receiver<T: Send> = Receiver<T>;
+
+    fn link<T: Send>() -> (SyncSender<T>, Receiver<T>) {
+        sync_channel(MPSC_CAPACITY)
+    }
+    fn send<T: Send>(sender: &SyncSender<T>, value: T) -> Result<(), T> {
+        sender.send(value).map_err(|e| e.0)
+    }
+    fn recv<T: Send>(receiver: &Receiver<T>) -> Option<T> {
+        receiver.recv().ok()
+    }
+}
+
+// Capacidad de cada enlace SPSC
+const SPSC_CAPACITY: usize = 1024;
+
+pub struct SpscLink;
+
+impl Link for SpscLink {
+    type Sender<T: Send> = spsc::Sender<T>;
+    type Receiver<T: Send> = spsc::Receiver<T>;
+
+    fn link<T: Send>() -> (spsc::Sender<T>, spsc::Receiver<T>) {
+        spsc::channel(SPSC_CAPACITY)
+    }
+    fn send<T: Send>(sender: &spsc::Sender<T>, value: T) -> Result<(), T> {
+        sender.send(value).map_err(|e| e.0)
+    }
+    fn recv<T: Send>(receiver: &spsc::Receiver<T>) -> Option<T> {
+        receiver.recv().ok()
+    }
+}
+
+// Entra un `I`, sale un `O`. Cada etapa corre en su propio hilo cuando se llama a `run` o
+// `run_stream`.
+pub struct Pipeline<I: Send, O: Send = I, L: Link = MpscLink> {
+    first_sender: L::Sender<I>,
+    last_receiver: L::Receiver<O>,
+    stages: Vec<(usize, Stage)>,
+    metrics: Vec<Arc<StageMetrics>>,
+    dead_letters: Receiver<DeadLetter>,
+}
+
+// Una etapa ya conectada a sus dos enlaces, lista para mandarla a un hilo. Va acompañada del
+// número de etapa para poder decir cuál falló.
+type Stage = Box<dyn FnOnce() + Send>;
+
+// Una entrada que una etapa de `try_stage` no pudo procesar
+pub struct DeadLetter {
+    pub stage: usize,
+    pub input: Box<dyn Any + Send>,
+    pub error: Box<dyn Error + Send + Sync>,
+}
+
+impl DeadLetter {
+    // La entrada original, si era de tipo `T`
+    pub fn input<T: 'static>(&self) -> Option<&T> {
+        self.input.downcast_ref()
+    }
+}
+
+impl fmt::Debug for DeadLetter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeadLetter")
+            .field("stage", &self.stage)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
 
+#[derive(Debug)]
+pub enum PipelineError {
+    // La entrada de `run` terminó en el canal de dead letters
+    DeadLetter(DeadLetter),
+    // La entrada de `run` falló pero su dead letter se descartó
+    Lost,
+    // `stage` es None si el que entró en pánico fue el iterador de entradas
+    StagePanicked {
+        stage: Option<usize>,
+        message: String,
+    },
 }
 
-struct Pipeline<T> {
-    first_sender: Sender<T>,
-    last_receiver: Receiver<T>,
-    nodes: Vec<PipelineNode<T>>,
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::DeadLetter(letter) => {
+                write!(f, "la etapa {} falló: {}", letter.stage, letter.error)
+            }
+            PipelineError::Lost => write!(f, "la entrada no salió del pipeline"),
+            PipelineError::StagePanicked {
+                stage: Some(stage),
+                message,
+            } => write!(f, "la etapa {stage} entró en pánico: {message}"),
+            PipelineError::StagePanicked {
+                stage: None,
+                message,
+            } => write!(f, "las entradas entraron en pánico: {message}"),
+        }
+    }
 }
 
+impl Error for PipelineError {}
+
 type NodeFunction<T> = fn(T) -> T;
-impl<T: Send + 'static> Pipeline<T> {
+
+impl Pipeline<(), ()> {
+    // Punto de entrada del builder: `Pipeline::source::<A>().stage(f).stage(g).sink()`.
+    // Para otro enlace: `PipelineBuilder::<A, A, SpscLink>::new()`
+    pub fn source<A: Send + 'static>() -> PipelineBuilder<A, A> {
+        PipelineBuilder::new()
+    }
+}
+
+impl<T: Send + 'static, L: Link + 'static> Pipeline<T, T, L> {
     pub fn new(funcs: Vec<NodeFunction<T>>) -> Self {
-        let (first_sender, mut prev_rx) = channel::<T>();
-        let (last_tx, last_rx) = channel::<T>();
-        let n = funcs.len();
-        let mut nodes = Vec::with_capacity(n);
-        for (i, f) in funcs.into_iter().enumerate() {
-            let (tx, rx) = if i + 1 == n {
-                (last_tx.clone(), { channel::<T>().1 })
-            } else {
-                channel::<T>()
-            };
+        funcs
+            .into_iter()
+            .fold(PipelineBuilder::new(), |builder, f| builder.stage(f))
+            .sink()
+    }
+}
 
-            nodes.push(PipelineNode {
-                id: i as i32,
-                sender: tx,
-                receiver: prev_rx,
-                pipeline_function: f,
-            });
+impl<I: Send + 'static, O: Send + 'static, L: Link + 'static> Pipeline<I, O, L> {
+    // Una entrada por etapa (las paralelas cuentan una vez), en el orden en que se agregaron
+    pub fn metrics(&self) -> &[Arc<StageMetrics>] {
+        &self.metrics
+    }
 
-            prev_rx = rx;
+    pub fn run(self, initial: I) -> Result<O, PipelineError> {
+        let mut stream = self.run_stream(std::iter::once(initial));
+        match stream.next() {
+            Some(result) => result,
+            // Ya terminaron todos los hilos, así que si falló la dead letter ya está en el canal
+            None => match stream.dead_letters().try_recv() {
+                Ok(letter) => Err(PipelineError::DeadLetter(letter)),
+                Err(_) => Err(PipelineError::Lost),
+            },
         }
+    }
 
-        Pipeline {
+    // Un hilo alimenta el pipeline con `inputs` y cierra la entrada al terminar; el cierre recorre
+    // las etapas una por una hasta que el stream devuelve None
+    pub fn run_stream<It>(self, inputs: It) -> PipelineStream<O, L>
+    where
+        It: IntoIterator<Item = I>,
+        It::IntoIter: Send + 'static,
+    {
+        let mut handles: Vec<_> = self
+            .stages
+            .into_iter()
+            .map(|(stage, run)| (Some(stage), thread::spawn(run)))
+            .collect();
+        let first_sender = self.first_sender;
+        let inputs = inputs.into_iter();
+        handles.push((
+            None,
+            thread::spawn(move || {
+                for input in inputs {
+                    // Si alguien soltó el stream, no hay a quién mandarle nada
+                    if L::send(&first_sender, input).is_err() {
+                        break;
+                    }
+                }
+            }),
+        ));
+        PipelineStream {
+            receiver: Some(self.last_receiver),
+            handles,
+            metrics: self.metrics,
+            dead_letters: self.dead_letters,
+        }
+    }
+}
+
+// Las salidas en el mismo orden que las entradas. Cuando se agota (o se suelta) espera a que
+// terminen todos los hilos del pipeline; si alguno entró en pánico, el último elemento es el
+// error correspondiente.
+pub struct PipelineStream<O: Send, L: Link = MpscLink> {
+    receiver: Option<L::Receiver<O>>,
+    handles: Vec<(Option<usize>, JoinHandle<()>)>,
+    metrics: Vec<Arc<StageMetrics>>,
+    dead_letters: Receiver<DeadLetter>,
+}
+
+impl<O: Send, L: Link> PipelineStream<O, L> {
+    pub fn metrics(&self) -> &[Arc<StageMetrics>] {
+        &self.metrics
+    }
+
+    // Se cierra cuando terminan todas las etapas
+    pub fn dead_letters(&self) -> &Receiver<DeadLetter> {
+        &self.dead_letters
+    }
+
+    // Devuelve los panics de las etapas que fallaron
+    fn shutdown(&mut self) -> Vec<PipelineError> {
+        // Primero el receptor, así la última etapa ve el cierre si todavía estaba mandando
+        self.receiver = None;
+        self.handles
+            .drain(..)
+            .filter_map(|(stage, handle)| {
+                let panic = handle.join().err()?;
+                Some(PipelineError::StagePanicked {
+                    stage,
+                    message: panic_message(panic),
+                })
+            })
+            .collect()
+    }
+}
+
+// El mensaje de un panic, que casi siempre es un &str o un String
+pub(crate) fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "(sin mensaje)".to_string(),
+        },
+    }
+}
+
+impl<O: Send, L: Link> Iterator for PipelineStream<O, L> {
+    type Item = Result<O, PipelineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(value) = L::recv(self.receiver.as_ref()?) {
+            return Some(Ok(value));
+        }
+        // Se cerró: si fue por un panic lo reportamos (el primero) y después termina
+        self.shutdown().into_iter().next().map(Err)
+    }
+}
+
+impl<O: Send, L: Link> Drop for PipelineStream<O, L> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// Va acumulando etapas; `O` es el tipo que sale de la última, así que la siguiente tiene que
+// aceptarlo y un pipeline mal armado no compila
+pub struct PipelineBuilder<I: Send, O: Send, L: Link = MpscLink> {
+    first_sender: L::Sender<I>,
+    last_receiver: L::Receiver<O>,
+    stages: Vec<(usize, Stage)>,
+    metrics: Vec<Arc<StageMetrics>>,
+    dead_letters: (SyncSender<DeadLetter>, Receiver<DeadLetter>),
+}
+
+impl<I: Send + 'static, L: Link + 'static> PipelineBuilder<I, I, L> {
+    pub fn new() -> Self {
+        let (first_sender, last_receiver) = L::link::<I>();
+        PipelineBuilder {
             first_sender,
-            nodes,
-            last_receiver: last_rx,
+            last_receiver,
+            stages: Vec::new(),
+            metrics: Vec::new(),
+            dead_letters: sync_channel(DEAD_LETTER_CAPACITY),
         }
     }
+}
 
-    pub fn run(self, initial: T) -> T {
-        for node in self.nodes {
-            thread::spawn(move || {
-                loop {
-                    let x = node.receiver.recv().expect("canal cerrado");
-                    let y = (node.pipeline_function)(x);
-                    node.sender.send(y).expect("no se puede enviar");
-                    println!("Nodo {} procesó un valor", node.id);
-                }
-            });
+impl<I: Send + 'static, O: Send + 'static, L: Link + 'static> PipelineBuilder<I, O, L> {
+    // La etapa nueva lee de la salida actual y escribe en un enlace nuevo
+    pub fn stage<P, F>(self, mut f: F) -> PipelineBuilder<I, P, L>
+    where
+        P: Send + 'static,
+        F: FnMut(O) -> P + Send + 'static,
+    {
+        self.filter_stage(move |x| Some(f(x)))
+    }
+
+    // Como `stage`, pero si `f` devuelve Err la entrada original va a dead letters junto con el
+    // error y la etapa sigue con la próxima. Por eso `f` recibe la entrada prestada: si necesita
+    // quedarse con algo, lo copia ella.
+    pub fn try_stage<P, E, F>(self, mut f: F) -> PipelineBuilder<I, P, L>
+    where
+        P: Send + 'static,
+        E: Error + Send + Sync + 'static,
+        F: FnMut(&O) -> Result<P, E> + Send + 'static,
+    {
+        let stage = self.metrics.len();
+        let dead_letters = self.dead_letters.0.clone();
+        self.filter_stage(move |x: O| match f(&x) {
+            Ok(y) => Some(y),
+            Err(error) => {
+                // Si el canal está lleno es que nadie las lee: se descartan en vez de frenar la
+                // etapa o acumularlas sin límite
+                let _ = dead_letters.try_send(DeadLetter {
+                    stage,
+                    input: Box::new(x),
+                    error: Box::new(error),
+                });
+                None
+            }
+        })
+    }
+
+    // Etapa de un hilo que manda al enlace siguiente sólo los Some
+    fn filter_stage<P, F>(mut self, f: F) -> PipelineBuilder<I, P, L>
+    where
+        P: Send + 'static,
+        F: FnMut(O) -> Option<P> + Send + 'static,
+    {
+        let (tx, rx) = L::link::<P>();
+        let stage = self.metrics.len();
+        let metrics = Arc::new(StageMetrics::new(stage, 1));
+        let node = PipelineNode::<O, P, L, F>::new(tx, self.last_receiver, f, Arc::clone(&metrics));
+        self.stages.push((stage, Box::new(move || node.run())));
+        self.metrics.push(metrics);
+        PipelineBuilder {
+            first_sender: self.first_sender,
+            last_receiver: rx,
+            stages: self.stages,
+            metrics: self.metrics,
+            dead_letters: self.dead_letters,
         }
+    }
 
-        self.first_sender
-            .send(initial)
-            .expect("Error sending message");
-        self.last_receiver.recv().unwrap()
+    // Fan-out/fan-in: `workers` hilos toman entradas del mismo receptor y un hilo colector junta
+    // sus salidas en el enlace siguiente. Con `ordered` el colector las devuelve en el orden de
+    // entrada; si no, en el orden en que terminan.
+    pub fn stage_parallel<P, F>(
+        mut self,
+        workers: usize,
+        ordered: bool,
+        f: F,
+    ) -> PipelineBuilder<I, P, L>
+    where
+        P: Send + 'static,
+        F: Fn(O) -> P + Send + Sync + 'static,
+    {
+        assert!(workers > 0, "hace falta al menos un worker");
+        let (tx, rx) = L::link::<P>();
+        let stage = self.metrics.len();
+        let metrics = Arc::new(StageMetrics::new(stage, workers));
+        // El número de secuencia se asigna bajo el mismo lock que el recv, así que respeta el
+        // orden en que llegaron las entradas
+        let input = Arc::new(Mutex::new((0u64, self.last_receiver)));
+        let f = Arc::new(f);
+        // Varios workers mandan al colector, así que este enlace es mpsc sea cual sea `L`
+        let (results_tx, results_rx) = sync_channel::<(u64, P)>(MPSC_CAPACITY);
+        for _ in 0..workers {
+            let input = Arc::clone(&input);
+            let f = Arc::clone(&f);
+            let results = results_tx.clone();
+            let metrics = Arc::clone(&metrics);
+            self.stages.push((
+                stage,
+                Box::new(move || {
+                    loop {
+                        let (seq, x) = {
+                            let mut input = input.lock().unwrap();
+                            let Some(x) = L::recv(&input.1) else { break };
+                            input.0 += 1;
+                            (input.0 - 1, x)
+                        };
+                        let y = metrics.measure(|| f(x));
+                        if results.send((seq, y)).is_err() {
+                            break;
+                        }
+                    }
+                }),
+            ));
+        }
+        drop(results_tx);
+        self.stages.push((
+            stage,
+            Box::new(move || collect::<P, L>(results_rx, tx, ordered)),
+        ));
+        self.metrics.push(metrics);
+        PipelineBuilder {
+            first_sender: self.first_sender,
+            last_receiver: rx,
+            stages: self.stages,
+            metrics: self.metrics,
+            dead_letters: self.dead_letters,
+        }
+    }
+
+    // Nuestra copia del emisor de dead letters se descarta acá: el canal se cierra cuando
+    // terminan las etapas que tienen las suyas
+    pub fn sink(self) -> Pipeline<I, O, L> {
+        Pipeline {
+            first_sender: self.first_sender,
+            last_receiver: self.last_receiver,
+            stages: self.stages,
+            metrics: self.metrics,
+            dead_letters: self.dead_letters.1,
+        }
     }
 }
 
-struct PipelineNode<T> {
-    id: i32,
-    sender: Sender<T>,
-    receiver: Receiver<T>,
-    pipeline_function: fn(T) -> T,
+// Termina cuando todos los workers soltaron su emisor. Si el enlace siguiente se cierra, sale y
+// al soltar `results` los workers se enteran en su próximo send.
+fn collect<P: Send, L: Link>(results: Receiver<(u64, P)>, sender: L::Sender<P>, ordered: bool) {
+    let mut pending = BTreeMap::new();
+    let mut next = 0;
+    for (seq, y) in results {
+        if !ordered {
+            if L::send(&sender, y).is_err() {
+                return;
+            }
+            continue;
+        }
+        pending.insert(seq, y);
+        while let Some(y) = pending.remove(&next) {
+            if L::send(&sender, y).is_err() {
+                return;
+            }
+            next += 1;
+        }
+    }
+}
+
+// Contadores de una etapa, compartidos entre sus workers
+pub struct StageMetrics {
+    stage: usize,
+    workers: usize,
+    processed: AtomicUsize,
+    busy_nanos: AtomicU64,
 }
 
-impl<T: Send> PipelineNode<T> {
+impl StageMetrics {
+    fn new(stage: usize, workers: usize) -> Self {
+        StageMetrics {
+            stage,
+            workers,
+            processed: AtomicUsize::new(0),
+            busy_nanos: AtomicU64::new(0),
+        }
+    }
+
+    fn measure<R>(&self, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.busy_nanos
+            .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        self.processed.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    pub fn stage(&self) -> usize {
+        self.stage
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn processed(&self) -> usize {
+        self.processed.load(Ordering::Relaxed)
+    }
+
+    // Tiempo total dentro de la función, sumando todos los workers
+    pub fn busy(&self) -> Duration {
+        Duration::from_nanos(self.busy_nanos.load(Ordering::Relaxed))
+    }
+
+    // Ítems por segundo que la etapa sostiene con sus workers: la más baja es el cuello de botella
+    pub fn throughput(&self) -> f64 {
+        let per_worker = self.busy().as_secs_f64() / self.workers as f64;
+        if per_worker == 0.0 {
+            f64::INFINITY
+        } else {
+            self.processed() as f64 / per_worker
+        }
+    }
+}
+
+impl fmt::Display for StageMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "etapa {} ({} workers): {} ítems, {:.0} ítems/s",
+            self.stage,
+            self.workers,
+            self.processed(),
+            self.throughput()
+        )
+    }
+}
+
+struct PipelineNode<I: Send, O: Send, L: Link, F> {
+    sender: L::Sender<O>,
+    receiver: L::Receiver<I>,
+    pipeline_function: F,
+    metrics: Arc<StageMetrics>,
+}
+
+impl<I: Send, O: Send, L: Link, F: FnMut(I) -> Option<O>> PipelineNode<I, O, L, F> {
     pub fn new(
-        sender: Sender<T>,
-        receiver: Receiver<T>,
-        pipeline_function: fn(T) -> T,
-        id: i32,
+        sender: L::Sender<O>,
+        receiver: L::Receiver<I>,
+        pipeline_function: F,
+        metrics: Arc<StageMetrics>,
     ) -> Self {
         PipelineNode {
-            id,
             sender,
             receiver,
             pipeline_function,
+            metrics,
+        }
+    }
+
+    // Termina cuando se cierra la etapa anterior (y su emisor se suelta al salir, así que el cierre
+    // sigue hacia adelante) o cuando la siguiente ya no existe (y el cierre va hacia atrás)
+    fn run(mut self) {
+        while let Some(x) = L::recv(&self.receiver) {
+            let Some(y) = self.metrics.measure(|| (self.pipeline_function)(x)) else {
+                continue;
+            };
+            if L::send(&self.sender, y).is_err() {
+                break;
+            }
+            println!("Nodo {} procesó un valor", self.metrics.stage());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn functions() -> Vec<NodeFunction<i32>> {
+        vec![add_one, times_two, square, neg, add_ten]
+    }
+
+    // 1. Los dos tipos de enlace dan el mismo resultado: -(2 * (10 + 1))^2 + 10
+    #[test]
+    fn both_links_compute_the_same_value() {
+        let mpsc: Pipeline<i32, i32, MpscLink> = Pipeline::new(functions());
+        let spsc: Pipeline<i32, i32, SpscLink> = Pipeline::new(functions());
+        assert_eq!(mpsc.run(10).unwrap(), -474);
+        assert_eq!(spsc.run(10).unwrap(), -474);
+    }
+
+    // 2. Sin etapas el valor sale tal cual entró
+    #[test]
+    fn empty_pipeline_is_the_identity() {
+        let pipeline: Pipeline<i32, i32, SpscLink> = Pipeline::new(vec![]);
+        assert_eq!(pipeline.run(7).unwrap(), 7);
+    }
+
+    // 3. Comparación de los enlaces (correr con `cargo test -- --nocapture` para ver los tiempos)
+    #[test]
+    fn compare_links() {
+        fn time<L: Link + 'static>() -> std::time::Duration {
+            let t0 = Instant::now();
+            for i in 0..200 {
+                let pipeline: Pipeline<i32, i32, L> = Pipeline::new(functions());
+                assert_eq!(
+                    pipeline.run(i).unwrap(),
+                    -(2 * (i + 1)) * (2 * (i + 1)) + 10
+                );
+            }
+            t0.elapsed()
         }
+        println!("mpsc: {:?}", time::<MpscLink>());
+        println!("spsc: {:?}", time::<SpscLink>());
+    }
+
+    // 4. Cada etapa cambia el tipo y puede ser una closure con estado propio
+    #[test]
+    fn typed_stages_change_the_type() {
+        let factor = 2;
+        let pipeline = Pipeline::source::<String>()
+            .stage(|s: String| s.trim().parse::<i32>().unwrap())
+            .stage(move |x| x * factor)
+            .stage(|x| (x, x % 2 == 0))
+            .stage(|(x, even)| format!("{x}:{even}"))
+            .sink();
+        assert_eq!(pipeline.run(" 21 ".to_string()).unwrap(), "42:true");
+    }
+
+    // 5. Lo mismo armado sobre enlaces SPSC
+    #[test]
+    fn typed_builder_over_spsc_links() {
+        let pipeline = PipelineBuilder::<Vec<u8>, Vec<u8>, SpscLink>::new()
+            .stage(String::from_utf8)
+            .stage(|s| s.unwrap().len())
+            .sink();
+        assert_eq!(pipeline.run(b"hola".to_vec()).unwrap(), 4);
+    }
+
+    // 6. Muchas entradas, salidas en orden, con los dos enlaces
+    #[test]
+    fn stream_keeps_input_order() {
+        let expected: Vec<_> = (0..1_000)
+            .map(|i| -(2 * (i + 1)) * (2 * (i + 1)) + 10)
+            .collect();
+        let mpsc: Pipeline<i32, i32, MpscLink> = Pipeline::new(functions());
+        assert_eq!(
+            mpsc.run_stream(0..1_000)
+                .map(Result::unwrap)
+                .collect::<Vec<_>>(),
+            expected
+        );
+        let spsc: Pipeline<i32, i32, SpscLink> = Pipeline::new(functions());
+        assert_eq!(
+            spsc.run_stream(0..1_000)
+                .map(Result::unwrap)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    // 7. Al agotarse el stream todos los hilos terminaron: ninguna etapa tiene ya su copia del Arc
+    #[test]
+    fn closing_the_input_joins_every_stage() {
+        let alive = Arc::new(());
+        let mut builder = PipelineBuilder::<i32, i32, SpscLink>::new();
+        for _ in 0..5 {
+            let alive = Arc::clone(&alive);
+            builder = builder.stage(move |x| {
+                let _ = &alive;
+                x + 1
+            });
+        }
+        let outputs: Vec<_> = builder
+            .sink()
+            .run_stream(0..100)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(outputs, (5..105).collect::<Vec<_>>());
+        assert_eq!(Arc::strong_count(&alive), 1);
+    }
+
+    // 8. Soltar el stream antes de tiempo cierra el pipeline hacia atrás sin colgarse
+    #[test]
+    fn dropping_the_stream_early_shuts_down() {
+        let processed = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&processed);
+        let pipeline = Pipeline::source::<u64>()
+            .stage(move |x| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                x * 2
+            })
+            .sink();
+        let first: Vec<_> = pipeline
+            .run_stream(0..)
+            .take(3)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(first, vec![0, 2, 4]);
+        assert!(processed.load(Ordering::Relaxed) >= 3);
+    }
+
+    // 9. Con entradas infinitas el hilo que alimenta el pipeline no se adelanta: espera a que
+    // haya lugar en el primer enlace
+    #[test]
+    fn infinite_input_is_backpressured() {
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&pulled);
+        let inputs = (0u64..).inspect(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let pipeline = Pipeline::source::<u64>().stage(|x| x + 1).sink();
+        let mut stream = pipeline.run_stream(inputs);
+        assert_eq!(stream.next().unwrap().unwrap(), 1);
+        thread::sleep(Duration::from_millis(50));
+        // Lo que entra en los dos enlaces más lo que tiene en la mano cada hilo
+        assert!(pulled.load(Ordering::Relaxed) <= 2 * MPSC_CAPACITY + 3);
+    }
+
+    // 10. El panic de una etapa llega al que consume el stream como último elemento
+    #[test]
+    fn stage_panic_reaches_the_consumer() {
+        let pipeline = Pipeline::source::<i32>()
+            .stage(|x| if x == 3 { panic!("etapa rota") } else { x })
+            .sink();
+        let results: Vec<_> = pipeline.run_stream(0..10).collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[..3].iter().all(Result::is_ok));
+        match &results[3] {
+            Err(PipelineError::StagePanicked { stage, message }) => {
+                assert_eq!(*stage, Some(0));
+                assert_eq!(message, "etapa rota");
+            }
+            other => panic!("esperaba StagePanicked, llegó {other:?}"),
+        }
+    }
+
+    fn slow_double(x: u64) -> u64 {
+        thread::sleep(Duration::from_millis(2));
+        x * 2
+    }
+
+    // 11. Una etapa paralela ordenada devuelve lo mismo que la secuencial
+    #[test]
+    fn ordered_parallel_stage_keeps_input_order() {
+        let pipeline = Pipeline::source::<u64>()
+            .stage_parallel(4, true, slow_double)
+            .stage(|x| x + 1)
+            .sink();
+        let outputs: Vec<_> = pipeline.run_stream(0..100).map(Result::unwrap).collect();
+        assert_eq!(outputs, (0..100).map(|x| x * 2 + 1).collect::<Vec<_>>());
+    }
+
+    // 12. Sin reordenar llegan todas, en cualquier orden
+    #[test]
+    fn unordered_parallel_stage_loses_nothing() {
+        let pipeline = PipelineBuilder::<u64, u64, SpscLink>::new()
+            .stage_parallel(3, false, |x| x * 2)
+            .sink();
+        let mut outputs: Vec<_> = pipeline.run_stream(0..10_000).map(Result::unwrap).collect();
+        outputs.sort_unstable();
+        assert_eq!(outputs, (0..10_000).map(|x| x * 2).collect::<Vec<_>>());
+    }
+
+    // 13. Con la etapa lenta secuencial es el cuello de botella; con 4 workers deja de serlo
+    // (`cargo test -- --nocapture` muestra los contadores)
+    #[test]
+    fn parallel_stage_removes_the_bottleneck() {
+        fn measure(workers: usize) -> (Duration, Vec<Arc<StageMetrics>>) {
+            let pipeline = Pipeline::source::<u64>()
+                .stage(|x| x + 1)
+                .stage_parallel(workers, true, slow_double)
+                .stage(|x| x - 1)
+                .sink();
+            let t0 = Instant::now();
+            let mut stream = pipeline.run_stream(0..100);
+            assert_eq!(stream.by_ref().filter(Result::is_ok).count(), 100);
+            let elapsed = t0.elapsed();
+            for m in stream.metrics() {
+                println!("{m}");
+                assert_eq!(m.processed(), 100);
+            }
+            (elapsed, stream.metrics().to_vec())
+        }
+        let (sequential, seq_metrics) = measure(1);
+        let (parallel, par_metrics) = measure(4);
+        println!("secuencial: {sequential:?}, paralelo: {parallel:?}");
+        assert!(parallel < sequential);
+        assert!(par_metrics[1].throughput() > seq_metrics[1].throughput());
+    }
+
+    // 14. Las entradas que fallan van a dead letters con su etapa y su valor original
+    #[test]
+    fn failed_items_go_to_dead_letters() {
+        let pipeline = Pipeline::source::<&str>()
+            .stage(str::trim)
+            .try_stage(|s| s.parse::<i32>())
+            .stage(|x| x * 10)
+            .sink();
+        let mut stream = pipeline.run_stream(vec!["1", " 2", "tres", "4 ", "", "6"]);
+        let outputs: Vec<_> = stream.by_ref().map(Result::unwrap).collect();
+        assert_eq!(outputs, vec![10, 20, 40, 60]);
+        let dead: Vec<_> = stream.dead_letters().iter().collect();
+        assert_eq!(dead.len(), 2);
+        assert!(dead.iter().all(|letter| letter.stage == 1));
+        assert_eq!(dead[0].input::<&str>(), Some(&"tres"));
+        assert_eq!(dead[1].input::<&str>(), Some(&""));
+        assert_eq!(dead[0].error.to_string(), "invalid digit found in string");
+    }
+
+    // 15. Con una sola entrada, `run` devuelve la dead letter como error
+    #[test]
+    fn run_reports_the_dead_letter() {
+        let pipeline = Pipeline::source::<String>()
+            .try_stage(|s| s.parse::<u8>())
+            .sink();
+        match pipeline.run("300".to_string()) {
+            Err(PipelineError::DeadLetter(letter)) => {
+                assert_eq!(letter.stage, 0);
+                assert_eq!(letter.input::<String>().unwrap(), "300");
+            }
+            other => panic!("esperaba DeadLetter, llegó {other:?}"),
+        }
+    }
+
+    // 16. Si nadie lee las dead letters no se acumulan ni frenan la etapa, y la entrada no
+    // necesita ser Clone
+    #[test]
+    fn unread_dead_letters_are_dropped() {
+        struct Input(i32);
+        let pipeline = Pipeline::source::<Input>()
+            .try_stage(|x: &Input| {
+                if x.0 % 2 == 0 {
+                    Ok(x.0)
+                } else {
+                    Err(fmt::Error)
+                }
+            })
+            .sink();
+        let total = 4 * DEAD_LETTER_CAPACITY as i32;
+        let mut stream = pipeline.run_stream((0..total).map(Input));
+        assert_eq!(stream.by_ref().count(), total as usize / 2);
+        assert_eq!(stream.dead_letters().iter().count(), DEAD_LETTER_CAPACITY);
+    }
+
+    // 17. Un panic en el medio ya no cuelga `run`: vuelve como error
+    #[test]
+    fn run_reports_a_panicking_stage() {
+        let pipeline = PipelineBuilder::<i32, i32, SpscLink>::new()
+            .stage(|x| x + 1)
+            .stage(|_: i32| -> i32 { panic!("no se puede enviar") })
+            .stage(|x| x * 2)
+            .sink();
+        let error = pipeline.run(1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "la etapa 1 entró en pánico: no se puede enviar"
+        );
     }
 }
@@ -38,6 +38,29 @@ struct Data<T> {
     tail: usize,
     size: usize,
 }
+
+impl<T> Data<T> {
+    fn push(&mut self, element: T) {
+        let i = self.head;
+        self.buffer[i] = Some(element);
+        self.head = (i + 1) % self.capacity;
+        self.size += 1;
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.size == 0 {
+            return None;
+        }
+        let i = self.tail;
+        let result = self.buffer[i].take();
+        self.tail = (i + 1) % self.capacity;
+        self.size -= 1;
+        result
+    }
+}
+
+// Se comparte entre hilos con un `Arc`: todas las operaciones toman `&self` y se sincronizan con el
+// Mutex. `add` espera en `not_full` y avisa por `not_empty`; `remove` al revés.
 pub struct ConcurrentCircularBuffer<T> {
     data: Mutex<Data<T>>,
     not_empty: Condvar,
@@ -45,31 +68,287 @@ pub struct ConcurrentCircularBuffer<T> {
 }
 
 impl<T> ConcurrentCircularBuffer<T> {
-    pub fn add(&mut self, element: T) {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "la capacidad tiene que ser positiva");
+        ConcurrentCircularBuffer {
+            data: Mutex::new(Data {
+                buffer: (0..capacity).map(|_| None).collect(),
+                capacity,
+                head: 0,
+                tail: 0,
+                size: 0,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.lock().unwrap().capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.lock().unwrap().size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        let data = self.data.lock().unwrap();
+        data.size == data.capacity
+    }
+
+    pub fn add(&self, element: T) {
         let mut data = self.data.lock().unwrap();
         while data.size == data.capacity {
             data = self.not_full.wait(data).unwrap();
         }
-        let i = data.head;
-        data.buffer[i] = Some(element);
-        data.head = (i + 1) % data.capacity;
-        data.size += 1;
+        data.push(element);
+        drop(data);
 
         self.not_empty.notify_one();
     }
-    pub fn remove(&mut self) -> T {
+    pub fn remove(&self) -> T {
         let mut data = self.data.lock().unwrap();
         while data.size == 0 {
             data = self.not_empty.wait(data).unwrap();
         }
+        let result = data.pop();
+        drop(data);
 
-        let i = data.tail;
-        let result = data.buffer[i].take();
-        data.tail = (i + 1) % data.capacity;
-        data.size -= 1;
-
-        self.not_empty.notify_one();
+        // Se liberó un lugar: el que espera es un productor
+        self.not_full.notify_one();
 
         result.unwrap()
     }
+
+    // `drain_available` como iterador: saca todo lo que hay con un solo lock y un solo aviso
+    pub fn drain(&self) -> impl Iterator<Item = T> {
+        self.drain_available().into_iter()
+    }
+
+    // Mete todo, esperando cuando se llena, de a tandas como `BoundedBuffer::put_all`
+    pub fn put_all<I: IntoIterator<Item = T>>(&self, items: I) {
+        let mut items = items.into_iter().peekable();
+        let mut data = self.data.lock().unwrap();
+        while items.peek().is_some() {
+            while data.size == data.capacity {
+                data = self.not_full.wait(data).unwrap();
+            }
+            let mut count = 0;
+            while data.size < data.capacity {
+                let Some(element) = items.next() else { break };
+                data.push(element);
+                count += 1;
+            }
+            notify(&self.not_empty, count);
+        }
+    }
+
+    // Espera a que haya algo y se lleva hasta `n` de una vez, aunque sean menos
+    pub fn take_up_to(&self, n: usize) -> Vec<T> {
+        assert!(n > 0, "hay que pedir al menos uno");
+        let mut data = self.data.lock().unwrap();
+        while data.size == 0 {
+            data = self.not_empty.wait(data).unwrap();
+        }
+        let result: Vec<T> = (0..n.min(data.size)).filter_map(|_| data.pop()).collect();
+        drop(data);
+        notify(&self.not_full, result.len());
+        result
+    }
+
+    // Todo lo que hay en este momento, sin esperar
+    pub fn drain_available(&self) -> Vec<T> {
+        let mut data = self.data.lock().unwrap();
+        let result: Vec<T> = std::iter::from_fn(|| data.pop()).collect();
+        drop(data);
+        notify(&self.not_full, result.len());
+        result
+    }
+}
+
+// Con varios elementos nuevos (o lugares libres) puede seguir más de uno
+fn notify(condvar: &Condvar, count: usize) {
+    match count {
+        0 => {}
+        1 => condvar.notify_one(),
+        _ => condvar.notify_all(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    // Falla en vez de colgarse si el hilo queda esperando para siempre
+    fn join_within<R: Send + 'static>(handle: thread::JoinHandle<R>) -> R {
+        let (done_tx, done_rx) = channel();
+        thread::spawn(move || done_tx.send(handle.join()).unwrap());
+        done_rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("el hilo quedó bloqueado")
+            .unwrap()
+    }
+
+    // 1. take_up_to se lleva lo que haya, drain_available no espera, y el orden se mantiene al
+    // dar la vuelta
+    #[test]
+    fn test_batch_operations_keep_order_across_wraparound() {
+        let buffer = ConcurrentCircularBuffer::new(4);
+        assert!(buffer.drain_available().is_empty());
+        buffer.put_all([1, 2, 3]);
+        assert_eq!(buffer.take_up_to(2), vec![1, 2]);
+        buffer.put_all([4, 5, 6]);
+        assert_eq!(buffer.take_up_to(10), vec![3, 4, 5, 6]);
+        buffer.put_all([7]);
+        assert_eq!(buffer.drain_available(), vec![7]);
+    }
+
+    // 2. put_all más grande que la capacidad avanza a medida que el consumidor saca
+    #[test]
+    fn test_put_all_larger_than_capacity() {
+        let buffer = Arc::new(ConcurrentCircularBuffer::new(3));
+        let producer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || buffer.put_all(0..100))
+        };
+        let mut received = Vec::new();
+        while received.len() < 100 {
+            let batch = buffer.take_up_to(2);
+            assert!(!batch.is_empty() && batch.len() <= 2);
+            received.extend(batch);
+        }
+        producer.join().unwrap();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+    }
+
+    // 3. new arranca vacío
+    #[test]
+    fn test_new_is_empty() {
+        let buffer = ConcurrentCircularBuffer::<i32>::new(3);
+        assert_eq!(buffer.capacity(), 3);
+        assert_eq!(buffer.len(), 0);
+        assert!(buffer.is_empty());
+        assert!(!buffer.is_full());
+    }
+
+    // 4. capacidad cero no tiene sentido
+    #[test]
+    #[should_panic]
+    fn test_new_zero_capacity() {
+        ConcurrentCircularBuffer::<i32>::new(0);
+    }
+
+    // 5. add y remove en orden FIFO, hasta llenarlo
+    #[test]
+    fn test_add_remove_fifo() {
+        let buffer = ConcurrentCircularBuffer::new(3);
+        buffer.add(1);
+        buffer.add(2);
+        buffer.add(3);
+        assert!(buffer.is_full());
+        assert_eq!(buffer.remove(), 1);
+        assert_eq!(buffer.remove(), 2);
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(buffer.remove(), 3);
+        assert!(buffer.is_empty());
+    }
+
+    // 6. el orden se mantiene al dar la vuelta
+    #[test]
+    fn test_wraparound() {
+        let buffer = ConcurrentCircularBuffer::new(2);
+        for i in 0..10 {
+            buffer.add(i);
+            assert_eq!(buffer.remove(), i);
+        }
+    }
+
+    // 7. remove espera a que alguien agregue
+    #[test]
+    fn test_remove_waits_for_add() {
+        let buffer = Arc::new(ConcurrentCircularBuffer::new(1));
+        let consumer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || buffer.remove())
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!consumer.is_finished());
+        buffer.add(7);
+        assert_eq!(join_within(consumer), 7);
+    }
+
+    // 8. un productor esperando con el buffer lleno se despierta con el remove
+    #[test]
+    fn test_remove_wakes_blocked_producer() {
+        let buffer = Arc::new(ConcurrentCircularBuffer::new(1));
+        buffer.add(1);
+        let producer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || buffer.add(2))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!producer.is_finished());
+        assert_eq!(buffer.remove(), 1);
+        join_within(producer);
+        assert_eq!(buffer.remove(), 2);
+    }
+
+    // 9. varios productores y consumidores compartiendo el buffer con Arc
+    #[test]
+    fn test_many_producers_many_consumers() {
+        let buffer = Arc::new(ConcurrentCircularBuffer::new(4));
+        let producers: Vec<_> = (0..4)
+            .map(|p| {
+                let buffer = Arc::clone(&buffer);
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        buffer.add(p * 1000 + i);
+                    }
+                })
+            })
+            .collect();
+        let consumers: Vec<_> = (0..4)
+            .map(|_| {
+                let buffer = Arc::clone(&buffer);
+                thread::spawn(move || (0..250).map(|_| buffer.remove()).collect::<Vec<_>>())
+            })
+            .collect();
+        for p in producers {
+            join_within(p);
+        }
+        let mut received: Vec<_> = consumers.into_iter().flat_map(join_within).collect();
+        received.sort_unstable();
+        let expected: Vec<_> = (0..4)
+            .flat_map(|p| (0..250).map(move |i| p * 1000 + i))
+            .collect();
+        assert_eq!(received, expected);
+        assert!(buffer.is_empty());
+    }
+
+    // 10. drain saca lo que hay sin esperar y le hace lugar a los productores
+    #[test]
+    fn test_drain() {
+        let buffer = Arc::new(ConcurrentCircularBuffer::new(2));
+        assert_eq!(buffer.drain().count(), 0);
+        buffer.add(1);
+        buffer.add(2);
+        let producer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || buffer.add(3))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!producer.is_finished());
+        let mut drained: Vec<_> = buffer.drain().collect();
+        join_within(producer);
+        drained.extend(buffer.drain());
+        assert_eq!(drained, vec![1, 2, 3]);
+    }
 }
@@ -0,0 +1,280 @@
+/*
+7. **Monitor de salud (Heartbeat)**
+   - **Descripción:** Varios hilos trabajadores envían periódicamente un “latido” (por ejemplo, su ID y timestamp) a un hilo monitor que los supervisa. Si no recibe el latido de alguno en un plazo, lo considera caído.
+   - **Retos conceptuales:**
+     - Diferenciar mensajes de distintos trabajadores.
+     - Implementar timeouts sin bloquear el canal.
+ */
+
+// Todos los trabajadores mandan sus latidos `(id, timestamp)` por un mismo canal. El monitor
+// nunca se queda en un `recv` sin plazo mientras haya alguien vivo: espera con `recv_timeout`
+// hasta el primer vencimiento (último latido + plazo) y, haya llegado algo o no, revisa quién se
+// pasó. Un trabajador caído que vuelve a latir se da por recuperado.
+//
+// Un trabajador que termina bien suelta su `Heart` y se da de baja: no se lo reporta como caído.
+// Se pueden registrar varios `Heart` con el mismo id (por ejemplo, un trabajador con varios
+// hilos): comparten la entrada y el trabajador sigue registrado hasta que se suelta el último.
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthEvent {
+    WorkerDown(usize),
+    WorkerRecovered(usize),
+}
+
+enum Message {
+    Register(usize),
+    Beat { id: usize, at: Instant },
+    Deregister(usize),
+}
+
+pub struct HealthMonitor {
+    inbox: Sender<Message>,
+}
+
+// Lo tiene cada trabajador para latir
+pub struct Heart {
+    id: usize,
+    inbox: Sender<Message>,
+}
+
+struct Worker {
+    last_beat: Instant,
+    down: bool,
+    // Cuántos `Heart` vivos tiene este id
+    hearts: usize,
+}
+
+impl HealthMonitor {
+    // Los eventos llegan por el canal que se devuelve
+    pub fn new(timeout: Duration) -> (Self, Receiver<HealthEvent>) {
+        let (events, receiver) = channel();
+        let monitor = Self::with_callback(timeout, move |event| {
+            let _ = events.send(event);
+        });
+        (monitor, receiver)
+    }
+
+    // El callback corre en el hilo monitor: si tarda, demora la detección
+    pub fn with_callback<F>(timeout: Duration, on_event: F) -> Self
+    where
+        F: FnMut(HealthEvent) + Send + 'static,
+    {
+        let (inbox, messages) = channel();
+        thread::spawn(move || monitor(messages, timeout, on_event));
+        HealthMonitor { inbox }
+    }
+
+    // El plazo empieza a correr desde el registro, aunque todavía no haya latido
+    pub fn register(&self, id: usize) -> Heart {
+        self.inbox
+            .send(Message::Register(id))
+            .expect("el hilo monitor terminó");
+        Heart {
+            id,
+            inbox: self.inbox.clone(),
+        }
+    }
+}
+
+impl Heart {
+    pub fn beat(&self) {
+        // Si el monitor ya no existe no hay a quién avisarle
+        let _ = self.inbox.send(Message::Beat {
+            id: self.id,
+            at: Instant::now(),
+        });
+    }
+}
+
+impl Drop for Heart {
+    fn drop(&mut self) {
+        let _ = self.inbox.send(Message::Deregister(self.id));
+    }
+}
+
+// Termina cuando se sueltan el HealthMonitor y todos los Heart
+fn monitor<F: FnMut(HealthEvent)>(messages: Receiver<Message>, timeout: Duration, mut on_event: F) {
+    let mut workers: HashMap<usize, Worker> = HashMap::new();
+    loop {
+        let next_deadline = workers
+            .values()
+            .filter(|w| !w.down)
+            .map(|w| w.last_beat + timeout)
+            .min();
+        let message = match next_deadline {
+            Some(deadline) => {
+                messages.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => messages.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match message {
+            Ok(Message::Register(id)) => {
+                let worker = workers.entry(id).or_insert_with(|| Worker {
+                    last_beat: Instant::now(),
+                    down: false,
+                    hearts: 0,
+                });
+                worker.hearts += 1;
+            }
+            Ok(Message::Beat { id, at }) => {
+                if let Some(worker) = workers.get_mut(&id) {
+                    worker.last_beat = worker.last_beat.max(at);
+                    // Un latido que llega tarde no alcanza para darlo por recuperado
+                    if worker.down && at.elapsed() < timeout {
+                        worker.down = false;
+                        on_event(HealthEvent::WorkerRecovered(id));
+                    }
+                }
+            }
+            Ok(Message::Deregister(id)) => {
+                if let Some(worker) = workers.get_mut(&id) {
+                    worker.hearts -= 1;
+                    if worker.hearts == 0 {
+                        workers.remove(&id);
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        // Revisamos siempre, no sólo en el timeout: los latidos de los demás no deben demorar
+        // la detección de uno que se calló
+        let now = Instant::now();
+        for (&id, worker) in workers.iter_mut() {
+            if !worker.down && now.duration_since(worker.last_beat) >= timeout {
+                worker.down = true;
+                on_event(HealthEvent::WorkerDown(id));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    // Late cada PERIOD mientras `paused` esté apagado, hasta que se suelte `stop`
+    fn worker(heart: Heart, paused: Arc<AtomicBool>, stop: Receiver<()>) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(PERIOD) {
+                if !paused.load(Ordering::Relaxed) {
+                    heart.beat();
+                }
+            }
+        })
+    }
+
+    // 1. Un trabajador que late a tiempo nunca se da por caído
+    #[test]
+    fn healthy_worker_is_never_reported() {
+        let (monitor, events) = HealthMonitor::new(TIMEOUT);
+        let (stop, stopped) = channel();
+        let handle = worker(
+            monitor.register(1),
+            Arc::new(AtomicBool::new(false)),
+            stopped,
+        );
+        thread::sleep(TIMEOUT * 3);
+        drop(stop);
+        handle.join().unwrap();
+        assert!(events.try_recv().is_err());
+    }
+
+    // 2. Un trabajador trabado se reporta caído mientras el otro sigue sano, y se recupera al
+    // volver a latir
+    #[test]
+    fn stalled_worker_goes_down_and_recovers() {
+        let (monitor, events) = HealthMonitor::new(TIMEOUT);
+        let stalled = Arc::new(AtomicBool::new(false));
+        let (stop, stopped) = channel();
+        let (stop_other, other_stopped) = channel();
+        let handle = worker(monitor.register(1), Arc::clone(&stalled), stopped);
+        let other = worker(
+            monitor.register(2),
+            Arc::new(AtomicBool::new(false)),
+            other_stopped,
+        );
+        thread::sleep(TIMEOUT);
+        assert!(events.try_recv().is_err());
+
+        stalled.store(true, Ordering::Relaxed);
+        assert_eq!(
+            events.recv_timeout(TIMEOUT * 10),
+            Ok(HealthEvent::WorkerDown(1))
+        );
+        stalled.store(false, Ordering::Relaxed);
+        assert_eq!(
+            events.recv_timeout(TIMEOUT * 10),
+            Ok(HealthEvent::WorkerRecovered(1))
+        );
+
+        drop((stop, stop_other));
+        handle.join().unwrap();
+        other.join().unwrap();
+        assert!(events.try_recv().is_err());
+    }
+
+    // 3. Registrarse y no latir nunca también cuenta como caído; el callback recibe el evento
+    #[test]
+    fn silent_worker_is_reported_through_the_callback() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let monitor = {
+            let seen = Arc::clone(&seen);
+            HealthMonitor::with_callback(TIMEOUT, move |event| seen.lock().unwrap().push(event))
+        };
+        let _heart = monitor.register(7);
+        thread::sleep(TIMEOUT * 3);
+        assert_eq!(*seen.lock().unwrap(), vec![HealthEvent::WorkerDown(7)]);
+    }
+
+    // 4. El que suelta su Heart se da de baja y no se lo reporta
+    #[test]
+    fn finished_worker_is_not_reported() {
+        let (monitor, events) = HealthMonitor::new(TIMEOUT);
+        let heart = monitor.register(3);
+        heart.beat();
+        drop(heart);
+        assert_eq!(
+            events.recv_timeout(TIMEOUT * 3),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+
+    // 5. Con dos Heart del mismo id, soltar uno no da de baja al otro
+    #[test]
+    fn shared_id_stays_registered_until_the_last_heart() {
+        let (monitor, events) = HealthMonitor::new(TIMEOUT);
+        let first = monitor.register(4);
+        let second = monitor.register(4);
+        drop(first);
+        second.beat();
+        assert_eq!(
+            events.recv_timeout(TIMEOUT * 10),
+            Ok(HealthEvent::WorkerDown(4))
+        );
+        drop(second);
+    }
+
+    // 6. Sin monitor ni corazones el hilo termina y el canal de eventos se cierra
+    #[test]
+    fn events_channel_closes_when_everyone_leaves() {
+        let (monitor, events) = HealthMonitor::new(TIMEOUT);
+        let heart = monitor.register(1);
+        drop(monitor);
+        heart.beat();
+        drop(heart);
+        assert_eq!(
+            events.recv_timeout(TIMEOUT * 10),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+}
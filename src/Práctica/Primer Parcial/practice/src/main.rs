@@ -7,14 +7,21 @@ use std::thread::JoinHandle;
 
 mod bank_account;
 mod bounded_buffer;
+mod broker;
 mod circular_buffer;
+mod heartbeat;
 mod matrix;
 mod merge_sort;
+mod mpmc;
 mod parallel_vector_sum;
 mod philosophers;
+mod priority_dispatcher;
 mod queue;
 mod race_conditions;
+mod rate_limiter;
+mod rendezvous;
 mod channels;
+mod worker_pool;
 mod primer_parcial;
 
 fn main() {
@@ -22,23 +29,33 @@ fn main() {
 }
 
 fn bounded_buffer_main() {
-    let buffer: BoundedBuffer<i32> = BoundedBuffer::new(5);
+    let buffer: BoundedBuffer<i32> = BoundedBuffer::with_hook(5, |event| println!("{event:?}"));
     let buffer_ref = Arc::new(buffer);
-    let producers: Vec<Producer<i32>> = (0..5)
-        .map(|i| Producer::new(buffer_ref.clone(), i))
-        .collect();
+    // Con distinta cantidad de productores y consumidores: el buffer se cierra al irse el último
+    // productor y ahí terminan los consumidores
+    let first = Producer::new(buffer_ref.clone(), 0).expect("el buffer es nuevo");
+    let mut producers: Vec<Producer<i32>> = (1..3).map(|i| first.clone_with_id(i)).collect();
+    producers.push(first);
     let consumers: Vec<Consumer<i32>> = (0..5)
         .map(|i| Consumer::new(buffer_ref.clone(), i))
         .collect();
     let mut handles: Vec<JoinHandle<()>> = vec![];
 
-    for (i, p) in producers.into_iter().enumerate() {
-        let handle = thread::spawn(move || p.produce(i as i32));
+    for p in producers {
+        let handle = thread::spawn(move || {
+            for i in 0..4 {
+                let element = p.id() as i32 * 10 + i;
+                p.produce(element).unwrap();
+                println!("Producer with id {} produced {}", p.id(), element);
+            }
+        });
         handles.push(handle);
     }
     for c in consumers {
         let handle = thread::spawn(move || {
-            c.consume();
+            for element in c.iter() {
+                println!("Consumer with id {} consumed {}", c.id(), element);
+            }
         });
         handles.push(handle);
     }
@@ -0,0 +1,533 @@
+// Canal de varios productores y varios consumidores (MPMC), con el mismo diseño que el
+// `BoundedBuffer`: una cola en un Mutex y dos Condvar, `not_empty` y `not_full`. Sin capacidad
+// (`unbounded`) el send nunca espera.
+//
+// A diferencia de `std::sync::mpsc`, el `Receiver` se puede clonar: cada mensaje lo recibe uno
+// solo de los consumidores. Los errores son los de `std::sync::mpsc` y significan lo mismo: el
+// send falla cuando ya no queda ningún receptor, y el recv cuando no quedan emisores y la cola
+// está vacía (lo que se mandó antes de cerrar se puede seguir recibiendo).
+//
+// `select!` espera a la vez en varios receptores, con un plazo opcional. Quien selecciona deja una
+// señal registrada en cada canal, y cada send o desconexión la activa. Como los consumidores
+// compiten, que una señal se active no garantiza que el mensaje siga ahí: se vuelve a probar con
+// `try_recv` y, si otro se lo llevó, se sigue esperando.
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+struct State<T> {
+    buffer: VecDeque<T>,
+    capacity: Option<usize>,
+    senders: usize,
+    receivers: usize,
+    // Los `select!` que están esperando en este canal
+    selectors: Vec<Arc<Signal>>,
+}
+
+impl<T> State<T> {
+    fn is_full(&self) -> bool {
+        self.capacity.is_some_and(|c| self.buffer.len() == c)
+    }
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap()
+    }
+
+    // Hay algo para recibir, o no va a haber nunca más
+    fn wake_receivers(&self, state: &State<T>, all: bool) {
+        if all {
+            self.not_empty.notify_all();
+        } else {
+            self.not_empty.notify_one();
+        }
+        for signal in &state.selectors {
+            signal.notify();
+        }
+    }
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+pub fn bounded<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "la capacidad tiene que ser positiva");
+    new(Some(capacity))
+}
+
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    new(None)
+}
+
+fn new<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buffer: VecDeque::new(),
+            capacity,
+            senders: 1,
+            receivers: 1,
+            selectors: Vec::new(),
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+    });
+    let sender = Sender {
+        shared: Arc::clone(&shared),
+    };
+    (sender, Receiver { shared })
+}
+
+impl<T> Sender<T> {
+    // Espera mientras esté lleno
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let state = self.shared.lock();
+        let mut state = self
+            .shared
+            .not_full
+            .wait_while(state, |s| s.is_full() && s.receivers > 0)
+            .unwrap();
+        if state.receivers == 0 {
+            return Err(SendError(value));
+        }
+        state.buffer.push_back(value);
+        self.shared.wake_receivers(&state, false);
+        Ok(())
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.lock();
+        if state.receivers == 0 {
+            return Err(TrySendError::Disconnected(value));
+        }
+        if state.is_full() {
+            return Err(TrySendError::Full(value));
+        }
+        state.buffer.push_back(value);
+        self.shared.wake_receivers(&state, false);
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Sender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            self.shared.wake_receivers(&state, true);
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let state = self.shared.lock();
+        let state = self
+            .shared
+            .not_empty
+            .wait_while(state, |s| s.buffer.is_empty() && s.senders > 0)
+            .unwrap();
+        self.take(state).ok_or(RecvError)
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let state = self.shared.lock();
+        let disconnected = state.senders == 0;
+        self.take(state).ok_or(if disconnected {
+            TryRecvError::Disconnected
+        } else {
+            TryRecvError::Empty
+        })
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let state = self.shared.lock();
+        let (state, result) = self
+            .shared
+            .not_empty
+            .wait_timeout_while(state, timeout, |s| s.buffer.is_empty() && s.senders > 0)
+            .unwrap();
+        let timed_out = result.timed_out();
+        self.take(state).ok_or(if timed_out {
+            RecvTimeoutError::Timeout
+        } else {
+            RecvTimeoutError::Disconnected
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(|| self.recv().ok())
+    }
+
+    fn take(&self, mut state: MutexGuard<'_, State<T>>) -> Option<T> {
+        let value = state.buffer.pop_front()?;
+        drop(state);
+        self.shared.not_full.notify_one();
+        Some(value)
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().receivers += 1;
+        Receiver {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.receivers -= 1;
+        if state.receivers == 0 {
+            self.shared.not_full.notify_all();
+        }
+    }
+}
+
+// Cada `select!` espera en una sola de estas, registrada en todos sus canales
+#[derive(Default)]
+pub struct Signal {
+    notified: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl Signal {
+    fn notify(&self) {
+        *self.notified.lock().unwrap() = true;
+        self.changed.notify_one();
+    }
+
+    // false si venció el plazo
+    fn wait(&self, deadline: Option<Instant>) -> bool {
+        let notified = self.notified.lock().unwrap();
+        let mut notified = match deadline {
+            None => self.changed.wait_while(notified, |n| !*n).unwrap(),
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                let (notified, _) = self
+                    .changed
+                    .wait_timeout_while(notified, left, |n| !*n)
+                    .unwrap();
+                notified
+            }
+        };
+        std::mem::replace(&mut *notified, false)
+    }
+}
+
+// Lo que `select!` necesita de un receptor sin conocer el tipo de sus mensajes
+pub trait Selectable {
+    fn register(&self, signal: &Arc<Signal>);
+    fn unregister(&self, signal: &Arc<Signal>);
+    // Un `Result<T, RecvError>` si hay mensaje o el canal se cerró, None si está vacío
+    fn try_select(&self) -> Option<Box<dyn Any>>;
+}
+
+impl<T: 'static> Selectable for Receiver<T> {
+    fn register(&self, signal: &Arc<Signal>) {
+        self.shared.lock().selectors.push(Arc::clone(signal));
+    }
+
+    fn unregister(&self, signal: &Arc<Signal>) {
+        self.shared
+            .lock()
+            .selectors
+            .retain(|s| !Arc::ptr_eq(s, signal));
+    }
+
+    fn try_select(&self) -> Option<Box<dyn Any>> {
+        match self.try_recv() {
+            Ok(value) => Some(Box::new(Ok::<T, RecvError>(value))),
+            Err(TryRecvError::Disconnected) => Some(Box::new(Err::<T, RecvError>(RecvError))),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
+
+// Índice del receptor elegido y lo que se recibió de él. None si venció el plazo.
+pub(crate) fn select_any(
+    receivers: &[&dyn Selectable],
+    timeout: Option<Duration>,
+) -> Option<(usize, Box<dyn Any>)> {
+    // Arrancamos cada vez por uno distinto para que un canal muy activo no tape a los demás
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let start = NEXT.fetch_add(1, Ordering::Relaxed);
+    let signal = Arc::new(Signal::default());
+    // Registramos antes de mirar: un send que llega entre que miramos y nos dormimos deja la
+    // señal activada
+    for receiver in receivers {
+        receiver.register(&signal);
+    }
+    let selected = loop {
+        let ready = (0..receivers.len())
+            .map(|i| (start + i) % receivers.len())
+            .find_map(|i| Some((i, receivers[i].try_select()?)));
+        if ready.is_some() {
+            break ready;
+        }
+        if !signal.wait(deadline) && deadline.is_some_and(|d| Instant::now() >= d) {
+            break None;
+        }
+    };
+    for receiver in receivers {
+        receiver.unregister(&signal);
+    }
+    selected
+}
+
+// Para `select!`: recupera el tipo de lo que devolvió `select_any`
+pub(crate) fn unstash<T: 'static>(_: &Receiver<T>, value: Box<dyn Any>) -> Result<T, RecvError> {
+    *value
+        .downcast::<Result<T, RecvError>>()
+        .expect("el valor no es de este receptor")
+}
+
+// Espera en varios receptores y ejecuta el brazo del primero que tenga un mensaje (`Ok`) o se
+// haya cerrado (`Err(RecvError)`). Con `default(plazo)`, si no pasa nada a tiempo ejecuta ese.
+//
+//     select! {
+//         recv(altas) -> msg => println!("alta: {msg:?}"),
+//         recv(bajas) -> msg => println!("baja: {msg:?}"),
+//         default(Duration::from_secs(1)) => println!("nada"),
+//     }
+#[macro_export]
+macro_rules! select {
+    ($(recv($rx:expr) -> $res:pat => $body:expr),+ $(,)?) => {
+        $crate::select!(@bind None, unreachable!("sin plazo"); []; $(recv($rx) -> $res => $body,)+)
+    };
+    ($(recv($rx:expr) -> $res:pat => $body:expr,)+ default($timeout:expr) => $default:expr $(,)?) => {
+        $crate::select!(@bind Some($timeout), $default; []; $(recv($rx) -> $res => $body,)+)
+    };
+    // Evalúa cada receptor una sola vez. Todas las expansiones declaran `rx`, pero por la higiene
+    // de las macros cada una es una variable distinta y la lista las acumula a todas.
+    (@bind $timeout:expr, $default:expr; [$($bound:tt)*];
+        recv($rx:expr) -> $res:pat => $body:expr, $($rest:tt)*) => {{
+        let rx = &$rx;
+        $crate::select!(@bind $timeout, $default; [$($bound)* (rx, $res, $body)]; $($rest)*)
+    }};
+    (@bind $timeout:expr, $default:expr; [$(($rx:ident, $res:pat, $body:expr))+];) => {{
+        let receivers: &[&dyn $crate::mpmc::Selectable] = &[$($rx),+];
+        match $crate::mpmc::select_any(receivers, $timeout) {
+            Some((index, value)) => $crate::select!(@arm index, value; $(($rx, $res, $body))+),
+            None => $default,
+        }
+    }};
+    // Va descontando el índice hasta llegar al brazo elegido
+    (@arm $index:expr, $value:ident;) => {
+        unreachable!("índice fuera de rango")
+    };
+    (@arm $index:expr, $value:ident; ($rx:ident, $res:pat, $body:expr) $($rest:tt)*) => {
+        if $index == 0 {
+            let $res = $crate::mpmc::unstash($rx, $value);
+            $body
+        } else {
+            $crate::select!(@arm $index - 1, $value; $($rest)*)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // 1. Varios consumidores comparten el receptor y cada mensaje llega a uno solo
+    #[test]
+    fn many_producers_many_consumers() {
+        let (tx, rx) = bounded(4);
+        let producers: Vec<_> = (0..4)
+            .map(|p| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        tx.send(p * 1000 + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        let consumers: Vec<_> = (0..4)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.iter().collect::<Vec<_>>())
+            })
+            .collect();
+        drop(rx);
+        for p in producers {
+            p.join().unwrap();
+        }
+        let mut received: Vec<_> = consumers
+            .into_iter()
+            .flat_map(|c| c.join().unwrap())
+            .collect();
+        received.sort_unstable();
+        let expected: Vec<_> = (0..4)
+            .flat_map(|p| (0..500).map(move |i| p * 1000 + i))
+            .collect();
+        assert_eq!(received, expected);
+    }
+
+    // 2. Acotado: try_send falla lleno y send espera a que alguien reciba
+    #[test]
+    fn bounded_send_waits_for_room() {
+        let (tx, rx) = bounded(1);
+        tx.send(1).unwrap();
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+        let blocked = thread::spawn(move || tx.send(2));
+        thread::sleep(Duration::from_millis(50));
+        assert!(!blocked.is_finished());
+        assert_eq!(rx.recv(), Ok(1));
+        blocked.join().unwrap().unwrap();
+        assert_eq!(rx.recv(), Ok(2));
+    }
+
+    // 3. Desconexión como en std: se drena lo pendiente antes de reportar el cierre
+    #[test]
+    fn disconnection_matches_std() {
+        let (tx, rx) = unbounded();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Ok(2));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+
+        let (tx, rx) = bounded(1);
+        tx.send(1).unwrap();
+        let other = rx.clone();
+        drop(rx);
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+        drop(other);
+        // Lleno y sin receptores: no se queda esperando
+        assert_eq!(tx.send(3), Err(SendError(3)));
+        assert_eq!(tx.try_send(4), Err(TrySendError::Disconnected(4)));
+    }
+
+    // 4. select! espera en dos canales de tipos distintos, con plazo
+    #[test]
+    fn select_waits_on_several_receivers() {
+        let (numbers_tx, numbers) = unbounded::<i32>();
+        let (words_tx, words) = unbounded::<&str>();
+
+        let timed_out = select! {
+            recv(numbers) -> _ => false,
+            recv(words) -> _ => false,
+            default(Duration::from_millis(20)) => true,
+        };
+        assert!(timed_out);
+
+        let sender = {
+            let words_tx = words_tx.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                words_tx.send("hola").unwrap();
+            })
+        };
+        let got = select! {
+            recv(numbers) -> n => format!("número {n:?}"),
+            recv(words) -> w => format!("palabra {w:?}"),
+        };
+        assert_eq!(got, "palabra Ok(\"hola\")");
+        sender.join().unwrap();
+
+        // Un canal cerrado también está listo
+        drop(numbers_tx);
+        let closed = select! {
+            recv(numbers) -> n => n,
+            recv(words) -> _ => Ok(0),
+            default(Duration::from_secs(1)) => Ok(-1),
+        };
+        assert_eq!(closed, Err(RecvError));
+    }
+
+    // 5. Varios consumidores con select! sobre los mismos canales: nada se pierde ni se duplica
+    #[test]
+    fn competing_selects_receive_everything_once() {
+        let (high_tx, high) = bounded(8);
+        let (low_tx, low) = bounded(8);
+        let consumers: Vec<_> = (0..3)
+            .map(|_| {
+                let (high, low) = (high.clone(), low.clone());
+                thread::spawn(move || {
+                    let mut got = Vec::new();
+                    // Cuando se cierra uno, vaciamos el otro
+                    loop {
+                        select! {
+                            recv(high) -> m => match m {
+                                Ok(m) => got.push(m),
+                                Err(_) => break got.extend(low.iter()),
+                            },
+                            recv(low) -> m => match m {
+                                Ok(m) => got.push(m),
+                                Err(_) => break got.extend(high.iter()),
+                            },
+                        }
+                    }
+                    got
+                })
+            })
+            .collect();
+        drop((high, low));
+        for i in 0..1_000 {
+            if i % 3 == 0 {
+                low_tx.send(i).unwrap();
+            } else {
+                high_tx.send(i).unwrap();
+            }
+        }
+        drop((high_tx, low_tx));
+        let mut received: Vec<_> = consumers
+            .into_iter()
+            .flat_map(|c| c.join().unwrap())
+            .collect();
+        received.sort_unstable();
+        assert_eq!(received, (0..1_000).collect::<Vec<_>>());
+    }
+
+    // 6. Cada receptor se evalúa una sola vez, aunque se use para esperar y para recibir
+    #[test]
+    fn select_evaluates_each_receiver_once() {
+        let (tx, rx) = unbounded();
+        tx.send(7).unwrap();
+        let mut evaluated = 0;
+        let got = select! {
+            recv({
+                evaluated += 1;
+                rx.clone()
+            }) -> m => m,
+        };
+        assert_eq!(got, Ok(7));
+        assert_eq!(evaluated, 1);
+    }
+}
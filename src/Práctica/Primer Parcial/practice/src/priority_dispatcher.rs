@@ -0,0 +1,229 @@
+/*
+8. **Tareas con prioridades (colas separadas)**
+   - **Descripción:** Dispones de dos canales (alta y baja prioridad). Un “dispatcher” lee primero de la cola alta y, si está vacía, de la baja. Los productores escogen en qué canal enviar según urgencia.
+   - **Retos conceptuales:**
+     - Implementar la selección no bloqueante de múltiples canales (p. ej., comprobando `try_recv`).
+     - Prevenir starvation de la cola baja.
+ */
+
+// N niveles, el 0 es el más urgente. Cada nivel tiene su canal.
+//
+// Para no girar con `try_recv` sobre todos los canales, cada envío manda además un aviso por un
+// canal compartido (`wake`), después del elemento. El dispatcher se bloquea en `wake.recv()`, y
+// cada aviso que consume le garantiza que hay al menos un elemento esperando en algún nivel.
+//
+// Starvation: el primer elemento de cada nivel sube un nivel por cada `aging` que lleva esperando,
+// contado desde que entró o desde la última vez que se atendió su nivel, lo que sea más reciente.
+// Se elige el de mejor prioridad efectiva y, a igualdad, el de nivel más urgente. Contar sólo
+// desde que entró no alcanza: si la cola alta tiene atraso, sus elementos son más viejos que el
+// de la baja y también suben. Así, en cambio, el nivel que se acaba de atender arranca de cero y
+// un elemento del nivel k espera como mucho unos `(k + 1) * aging` más lo que tarde el que se
+// está procesando.
+use std::collections::VecDeque;
+use std::sync::mpsc::{Receiver, SendError, Sender, channel};
+use std::time::{Duration, Instant};
+
+pub struct PrioritySender<T> {
+    levels: Vec<Sender<(Instant, T)>>,
+    wake: Sender<()>,
+}
+
+impl<T> Clone for PrioritySender<T> {
+    fn clone(&self) -> Self {
+        PrioritySender {
+            levels: self.levels.clone(),
+            wake: self.wake.clone(),
+        }
+    }
+}
+
+pub struct PriorityDispatcher<T> {
+    levels: Vec<Receiver<(Instant, T)>>,
+    wake: Receiver<()>,
+    // Lo que ya sacamos de los canales pero todavía no entregamos
+    pending: Vec<VecDeque<(Instant, T)>>,
+    served: Vec<Instant>,
+    aging: Duration,
+}
+
+pub fn priority_channel<T>(
+    levels: usize,
+    aging: Duration,
+) -> (PrioritySender<T>, PriorityDispatcher<T>) {
+    assert!(levels > 0, "hace falta al menos un nivel");
+    assert!(!aging.is_zero(), "el aging tiene que ser positivo");
+    let (senders, receivers) = (0..levels).map(|_| channel()).unzip();
+    let (wake_tx, wake_rx) = channel();
+    let sender = PrioritySender {
+        levels: senders,
+        wake: wake_tx,
+    };
+    let dispatcher = PriorityDispatcher {
+        levels: receivers,
+        wake: wake_rx,
+        pending: (0..levels).map(|_| VecDeque::new()).collect(),
+        served: vec![Instant::now(); levels],
+        aging,
+    };
+    (sender, dispatcher)
+}
+
+impl<T> PrioritySender<T> {
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn send(&self, level: usize, item: T) -> Result<(), SendError<T>> {
+        assert!(level < self.levels.len(), "nivel {level} inexistente");
+        self.levels[level]
+            .send((Instant::now(), item))
+            .map_err(|SendError((_, item))| SendError(item))?;
+        // El aviso va después: quien lo recibe ya puede ver el elemento
+        let _ = self.wake.send(());
+        Ok(())
+    }
+}
+
+impl<T> PriorityDispatcher<T> {
+    // Bloquea hasta que haya algo. None cuando no quedan emisores ni elementos.
+    pub fn recv(&mut self) -> Option<(usize, T)> {
+        self.wake.recv().ok()?;
+        Some(self.pick())
+    }
+
+    pub fn try_recv(&mut self) -> Option<(usize, T)> {
+        self.wake.try_recv().ok()?;
+        Some(self.pick())
+    }
+
+    pub fn iter(&mut self) -> impl Iterator<Item = (usize, T)> + '_ {
+        std::iter::from_fn(|| self.recv())
+    }
+
+    // Sólo se llama después de consumir un aviso, así que hay al menos un elemento
+    fn pick(&mut self) -> (usize, T) {
+        self.pick_at(Instant::now())
+    }
+
+    // Con el instante como parámetro, los tests pueden simular el paso del tiempo
+    fn pick_at(&mut self, now: Instant) -> (usize, T) {
+        for (level, receiver) in self.levels.iter().enumerate() {
+            while let Ok(item) = receiver.try_recv() {
+                self.pending[level].push_back(item);
+            }
+        }
+        let levels = self.levels.len();
+        let (level, _) = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter_map(|(level, queue)| {
+                let (enqueued, _) = queue.front()?;
+                let age = now.duration_since((*enqueued).max(self.served[level]));
+                let boost = (age.as_nanos() / self.aging.as_nanos()) as usize;
+                Some((level, (levels - 1 - level).saturating_add(boost)))
+            })
+            // max_by_key se queda con el último empate: recorremos al revés para preferir el
+            // nivel más urgente
+            .rev()
+            .max_by_key(|&(_, score)| score)
+            .expect("hay un aviso por cada elemento");
+        let (_, item) = self.pending[level].pop_front().unwrap();
+        self.served[level] = now;
+        (level, item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    // 1. Sin aging que pese, sale primero lo más urgente y cada nivel en orden FIFO
+    #[test]
+    fn strict_priority_when_nothing_has_aged() {
+        let (tx, mut dispatcher) = priority_channel(3, HOUR);
+        tx.send(2, "baja 1").unwrap();
+        tx.send(0, "alta 1").unwrap();
+        tx.send(1, "media").unwrap();
+        tx.send(2, "baja 2").unwrap();
+        tx.send(0, "alta 2").unwrap();
+        drop(tx);
+        let order: Vec<_> = dispatcher.iter().collect();
+        assert_eq!(
+            order,
+            vec![
+                (0, "alta 1"),
+                (0, "alta 2"),
+                (1, "media"),
+                (2, "baja 1"),
+                (2, "baja 2")
+            ]
+        );
+    }
+
+    // 2. recv espera bloqueado hasta que llega algo a cualquier nivel
+    #[test]
+    fn recv_blocks_until_any_level_has_work() {
+        let (tx, mut dispatcher) = priority_channel::<i32>(4, HOUR);
+        assert_eq!(dispatcher.try_recv(), None);
+        let consumer = thread::spawn(move || dispatcher.recv());
+        thread::sleep(Duration::from_millis(50));
+        assert!(!consumer.is_finished());
+        tx.send(3, 42).unwrap();
+        assert_eq!(consumer.join().unwrap(), Some((3, 42)));
+    }
+
+    // 3. Varios productores: llega todo, y al irse todos el dispatcher termina
+    #[test]
+    fn many_producers_and_disconnect() {
+        let (tx, mut dispatcher) = priority_channel(2, HOUR);
+        let handles: Vec<_> = (0..4)
+            .map(|p| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        tx.send(i % tx.levels(), p * 1000 + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        for h in handles {
+            h.join().unwrap();
+        }
+        let mut received: Vec<_> = dispatcher.iter().map(|(_, item)| item).collect();
+        received.sort_unstable();
+        let expected: Vec<_> = (0..4)
+            .flat_map(|p| (0..250).map(move |i| p * 1000 + i))
+            .collect();
+        assert_eq!(received, expected);
+        assert_eq!(dispatcher.recv(), None);
+    }
+
+    // 4. La cola alta nunca se vacía y aun así la baja sale a tiempo. El tiempo es simulado: cada
+    // tarea tarda 1ms, y la baja tiene que salir antes de `(1 + 1) * aging` más una tarea.
+    #[test]
+    fn low_priority_worst_case_wait_is_bounded() {
+        let aging = Duration::from_millis(10);
+        let task = Duration::from_millis(1);
+        let (tx, mut dispatcher) = priority_channel(2, aging);
+        for _ in 0..500 {
+            tx.send(0, "alta").unwrap();
+        }
+        tx.send(1, "baja").unwrap();
+        let start = Instant::now();
+        let mut now = start;
+        // Con prioridad estricta esperaría a que se vacíen las 500 de la alta
+        let waited = loop {
+            let (level, _) = dispatcher.pick_at(now);
+            if level == 1 {
+                break now - start;
+            }
+            now += task;
+        };
+        assert!(waited <= aging * 2 + task, "esperó {waited:?}");
+    }
+}
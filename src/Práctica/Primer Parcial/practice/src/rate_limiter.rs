@@ -0,0 +1,412 @@
+/*
+5. **Rate Limiter por tokens**
+   - **Descripción:** Un hilo generador añade “tokens” a intervalos regulares a un canal, y los consumidores deben tomar un token antes de realizar su operación.
+   - **Retos conceptuales:**
+     - Controlar la frecuencia de producción de tokens.
+     - Evitar que los consumidores se bloqueen indefinidamente si no hay tokens.
+ */
+
+// El balde es un `sync_channel(burst)`: el generador mete tokens con `try_send` y, si está
+// lleno, el token se pierde. Así nunca se acumulan más de `burst`.
+//
+// Los tokens se calculan desde el inicio (los que tocan a esta altura son `transcurrido * rate`)
+// y no sumando esperas, así que el ritmo no se corre aunque el hilo se despierte tarde: si se
+// atrasó, recupera los tokens perdidos de una (hasta llenar el balde). Por lo mismo el generador
+// no se despierta más de una vez por `MIN_TICK`: con rates muy altos carga varios por vuelta en
+// lugar de girar sin dormir.
+//
+// El receptor del canal no se puede compartir, así que vive en un Mutex. Nadie se queda con el
+// lock mientras espera: el que no encuentra token duerme en un Condvar que el generador avisa con
+// cada token nuevo. El generador espera en el mismo Condvar, así que al soltar el RateLimiter se
+// lo despierta y termina enseguida. Todos los plazos se miden con el `Clock`.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MIN_TICK: Duration = Duration::from_millis(1);
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+// Tiempo transcurrido desde que se creó el reloj
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> Duration;
+
+    // Espera en `condvar` hasta que lo avisen o pase `timeout` de este reloj. Puede volver antes,
+    // así que el que llama vuelve a mirar qué pasó.
+    fn wait<'a, T>(
+        &self,
+        condvar: &Condvar,
+        guard: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, T>;
+}
+
+// Para poder quedarse con una copia del reloj y manejarlo desde afuera
+impl<C: Clock> Clock for Arc<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+
+    fn wait<'a, T>(
+        &self,
+        condvar: &Condvar,
+        guard: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, T> {
+        (**self).wait(condvar, guard, timeout)
+    }
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn wait<'a, T>(
+        &self,
+        condvar: &Condvar,
+        guard: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, T> {
+        condvar.wait_timeout(guard, timeout).unwrap().0
+    }
+}
+
+// Reloj de tests: el tiempo sólo avanza con `advance`
+pub struct ManualClock {
+    state: Mutex<ManualState>,
+    changed: Condvar,
+}
+
+struct ManualState {
+    now: Duration,
+    // Hasta cuándo duerme cada hilo que está en `wait`
+    deadlines: Vec<Duration>,
+}
+
+// Cada cuánto tiempo real mira el reloj un hilo que está en `wait`
+const MANUAL_POLL: Duration = Duration::from_millis(1);
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock {
+            state: Mutex::new(ManualState {
+                now: Duration::ZERO,
+                deadlines: Vec::new(),
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.state.lock().unwrap().now += duration;
+        self.changed.notify_all();
+    }
+
+    // Espera a que haya `sleepers` hilos dormidos hasta un momento todavía futuro. Después de un
+    // `advance`, sirve para saber que el generador ya procesó todos los ticks que le tocaban.
+    pub fn wait_for_sleepers(&self, sleepers: usize) {
+        let state = self.state.lock().unwrap();
+        let _state = self
+            .changed
+            .wait_while(state, |s| {
+                s.deadlines.iter().filter(|d| **d > s.now).count() < sleepers
+            })
+            .unwrap();
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.state.lock().unwrap().now
+    }
+
+    // `advance` no sabe avisarle a `condvar`, así que se mira el reloj cada MANUAL_POLL
+    fn wait<'a, T>(
+        &self,
+        condvar: &Condvar,
+        mut guard: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, T> {
+        if timeout.is_zero() {
+            return guard;
+        }
+        let deadline = {
+            let mut state = self.state.lock().unwrap();
+            let deadline = state.now + timeout;
+            state.deadlines.push(deadline);
+            deadline
+        };
+        self.changed.notify_all();
+        loop {
+            let (next, result) = condvar.wait_timeout(guard, MANUAL_POLL).unwrap();
+            guard = next;
+            if !result.timed_out() || self.now() >= deadline {
+                break;
+            }
+        }
+        let mut state = self.state.lock().unwrap();
+        let i = state.deadlines.iter().position(|d| *d == deadline).unwrap();
+        state.deadlines.swap_remove(i);
+        guard
+    }
+}
+
+struct Bucket<C> {
+    tokens: Mutex<Receiver<()>>,
+    // Lo avisan el generador con cada token nuevo y `drop` para que el generador termine
+    added: Condvar,
+    closed: AtomicBool,
+    clock: C,
+}
+
+pub struct RateLimiter<C: Clock = SystemClock> {
+    bucket: Arc<Bucket<C>>,
+}
+
+impl RateLimiter {
+    // `rate` tokens por segundo, como mucho `burst` acumulados. Arranca con el balde lleno.
+    pub fn new(rate: u32, burst: usize) -> Self {
+        Self::with_clock(rate, burst, SystemClock::new())
+    }
+}
+
+impl<C: Clock> RateLimiter<C> {
+    pub fn with_clock(rate: u32, burst: usize, clock: C) -> Self {
+        assert!(rate > 0, "el rate tiene que ser positivo");
+        assert!(burst > 0, "el burst tiene que ser positivo");
+        let (tx, rx) = sync_channel(burst);
+        for _ in 0..burst {
+            tx.try_send(()).unwrap();
+        }
+        let bucket = Arc::new(Bucket {
+            tokens: Mutex::new(rx),
+            added: Condvar::new(),
+            closed: AtomicBool::new(false),
+            clock,
+        });
+        let generator = Arc::clone(&bucket);
+        thread::spawn(move || generate(generator, rate, tx));
+        RateLimiter { bucket }
+    }
+
+    // Espera lo que haga falta
+    pub fn acquire(&self) {
+        let tokens = self.bucket.tokens.lock().unwrap();
+        let _tokens = self
+            .bucket
+            .added
+            .wait_while(tokens, |rx| rx.try_recv().is_err())
+            .unwrap();
+    }
+
+    pub fn try_acquire(&self) -> bool {
+        self.bucket.tokens.lock().unwrap().try_recv().is_ok()
+    }
+
+    // Con plazo medido en el reloj del limiter, así ningún consumidor se queda colgado
+    pub fn acquire_timeout(&self, timeout: Duration) -> bool {
+        let clock = &self.bucket.clock;
+        let deadline = clock.now() + timeout;
+        let mut tokens = self.bucket.tokens.lock().unwrap();
+        loop {
+            if tokens.try_recv().is_ok() {
+                return true;
+            }
+            let now = clock.now();
+            if now >= deadline {
+                return false;
+            }
+            tokens = clock.wait(&self.bucket.added, tokens, deadline - now);
+        }
+    }
+}
+
+impl<C: Clock> Drop for RateLimiter<C> {
+    fn drop(&mut self) {
+        self.bucket.closed.store(true, Ordering::Relaxed);
+        // Con el lock: el generador mira `closed` con el lock tomado antes de cada espera
+        let _tokens = self.bucket.tokens.lock().unwrap();
+        self.bucket.added.notify_all();
+    }
+}
+
+// Termina apenas se suelta el RateLimiter
+fn generate<C: Clock>(bucket: Arc<Bucket<C>>, rate: u32, tokens: SyncSender<()>) {
+    let rate = u128::from(rate);
+    let start = bucket.clock.now();
+    let mut added = 0;
+    let mut guard = bucket.tokens.lock().unwrap();
+    while !bucket.closed.load(Ordering::Relaxed) {
+        let now = bucket.clock.now();
+        let due = (now - start).as_nanos() * rate / NANOS_PER_SEC;
+        if due > added {
+            for _ in added..due {
+                // Con el balde lleno los que faltan se pierden
+                if let Err(TrySendError::Full(())) = tokens.try_send(()) {
+                    break;
+                }
+            }
+            added = due;
+            // Avisamos con el lock tomado: el que vio el balde vacío ya está esperando en el
+            // Condvar. A todos, porque el despertado podría ser uno cuyo plazo acaba de vencer
+            bucket.added.notify_all();
+        }
+        // Hasta el próximo token, redondeando para arriba así ya le toca al despertar
+        let next = start
+            + Duration::from_nanos(((added + 1) * NANOS_PER_SEC).div_ceil(rate) as u64);
+        let timeout = next.saturating_sub(now).max(MIN_TICK);
+        guard = bucket.clock.wait(&bucket.added, guard, timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10 tokens por segundo: uno cada 100ms
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    fn manual(burst: usize) -> (RateLimiter<Arc<ManualClock>>, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new());
+        let limiter = RateLimiter::with_clock(10, burst, Arc::clone(&clock));
+        clock.wait_for_sleepers(1);
+        (limiter, clock)
+    }
+
+    fn drain<C: Clock>(limiter: &RateLimiter<C>) -> usize {
+        let mut taken = 0;
+        while limiter.try_acquire() {
+            taken += 1;
+        }
+        taken
+    }
+
+    // 1. Arranca con `burst` tokens y después no hay más hasta el próximo tick
+    #[test]
+    fn starts_with_a_full_bucket() {
+        let (limiter, _clock) = manual(3);
+        assert_eq!(drain(&limiter), 3);
+        assert!(!limiter.try_acquire());
+    }
+
+    // 2. En 550ms de reloj entran exactamente 5 tokens
+    #[test]
+    fn produces_tokens_at_the_configured_rate() {
+        let (limiter, clock) = manual(10);
+        drain(&limiter);
+        clock.advance(INTERVAL * 5 + INTERVAL / 2);
+        clock.wait_for_sleepers(1);
+        assert_eq!(drain(&limiter), 5);
+        clock.advance(INTERVAL / 2);
+        clock.wait_for_sleepers(1);
+        assert_eq!(drain(&limiter), 1);
+    }
+
+    // 3. Por más tiempo que pase, nunca hay más de `burst`
+    #[test]
+    fn burst_caps_the_bucket() {
+        let (limiter, clock) = manual(3);
+        drain(&limiter);
+        clock.advance(INTERVAL * 100);
+        clock.wait_for_sleepers(1);
+        assert_eq!(drain(&limiter), 3);
+    }
+
+    // 4. acquire espera al próximo token
+    #[test]
+    fn acquire_blocks_until_a_token_arrives() {
+        let (limiter, clock) = manual(1);
+        let limiter = Arc::new(limiter);
+        drain(&limiter);
+        let waiter = {
+            let limiter = Arc::clone(&limiter);
+            thread::spawn(move || limiter.acquire())
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        clock.advance(INTERVAL);
+        waiter.join().unwrap();
+    }
+
+    // 5. Sin tokens, acquire_timeout se rinde al vencer el plazo, medido con el reloj del limiter
+    #[test]
+    fn acquire_timeout_gives_up() {
+        let (limiter, clock) = manual(1);
+        let limiter = Arc::new(limiter);
+        drain(&limiter);
+        let waiter = {
+            let limiter = Arc::clone(&limiter);
+            thread::spawn(move || limiter.acquire_timeout(INTERVAL / 2))
+        };
+        // El generador y el que espera
+        clock.wait_for_sleepers(2);
+        clock.advance(INTERVAL / 2);
+        assert!(!waiter.join().unwrap());
+        clock.advance(INTERVAL / 2);
+        assert!(limiter.acquire_timeout(INTERVAL));
+    }
+
+    // 6. Con el reloj real, 5 tokens a 100/s después de vaciar el balde tardan ~50ms
+    #[test]
+    fn system_clock_limits_real_time() {
+        let limiter = RateLimiter::new(100, 1);
+        drain(&limiter);
+        let t0 = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire();
+        }
+        assert!(t0.elapsed() >= Duration::from_millis(40));
+    }
+
+    // 7. Con un rate más fino que un nanosegundo por token el generador igual duerme entre
+    // vueltas, y carga de una todos los que tocan
+    #[test]
+    fn huge_rates_refill_in_batches() {
+        let clock = Arc::new(ManualClock::new());
+        let limiter = RateLimiter::with_clock(u32::MAX, 1000, Arc::clone(&clock));
+        clock.wait_for_sleepers(1);
+        assert_eq!(drain(&limiter), 1000);
+        clock.advance(MIN_TICK);
+        clock.wait_for_sleepers(1);
+        assert_eq!(drain(&limiter), 1000);
+    }
+
+    // 8. Al soltar el limiter el generador termina aunque el reloj no avance más
+    #[test]
+    fn generator_stops_on_drop() {
+        let (limiter, _clock) = manual(1);
+        let bucket = Arc::downgrade(&limiter.bucket);
+        drop(limiter);
+        let t0 = Instant::now();
+        while bucket.strong_count() > 0 {
+            assert!(t0.elapsed() < Duration::from_secs(5), "el generador sigue vivo");
+            thread::yield_now();
+        }
+    }
+}
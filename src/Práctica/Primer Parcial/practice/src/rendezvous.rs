@@ -0,0 +1,334 @@
+/*
+6. **Handshake (Rendezvous) de dos actores**
+   - **Descripción:** Dos hilos A y B deben sincronizarse en un punto: A envía un mensaje por un canal y espera recibir otro de vuelta, y viceversa.
+   - **Retos conceptuales:**
+     - Evitar deadlocks en la espera mutua.
+     - Decidir orden de envío/recepción.
+ */
+
+// Dos versiones:
+//
+// - `Exchanger<T>` (Mutex + Condvar): cualquier hilo llama a `exchange` y se empareja con el
+//   próximo que llegue. El primero deja su valor y espera; el segundo se lleva ese valor y deja el
+//   suyo. Si vence el plazo antes de que llegue alguien, el primero retira su oferta.
+//
+// - `Rendezvous` (canales): un par fijo de extremos A y B. El deadlock clásico es que los dos
+//   hagan `send` sobre un canal sin buffer (`sync_channel(0)`) y se queden esperando a que el otro
+//   haga `recv`. Con un lugar de buffer por sentido el send nunca espera a que el otro reciba, así
+//   que los dos pueden mandar primero y recibir después sin ponerse de acuerdo en el orden.
+use std::fmt;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, sync_channel};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+pub struct Exchanger<T> {
+    slot: Mutex<Slot<T>>,
+    changed: Condvar,
+}
+
+struct Slot<T> {
+    state: State<T>,
+    next_id: u64,
+}
+
+enum State<T> {
+    Empty,
+    // Alguien dejó su valor y espera pareja
+    Waiting { id: u64, value: T },
+    // La pareja ya dejó la respuesta; falta que el que esperaba la retire
+    Done { id: u64, value: T },
+}
+
+impl<T> Exchanger<T> {
+    pub fn new() -> Self {
+        Exchanger {
+            slot: Mutex::new(Slot {
+                state: State::Empty,
+                next_id: 0,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn exchange(&self, value: T) -> T {
+        match self.exchange_until(value, None) {
+            Ok(theirs) => theirs,
+            Err(_) => unreachable!("sin plazo no hay timeout"),
+        }
+    }
+
+    // Err devuelve nuestro propio valor si nadie llegó a tiempo
+    pub fn exchange_timeout(&self, value: T, timeout: Duration) -> Result<T, T> {
+        self.exchange_until(value, Some(Instant::now() + timeout))
+    }
+
+    fn exchange_until(&self, value: T, deadline: Option<Instant>) -> Result<T, T> {
+        let mut slot = self.slot.lock().unwrap();
+        // Si otra pareja está terminando, esperamos a que libere el lugar
+        while matches!(slot.state, State::Done { .. }) {
+            slot = match self.wait(slot, deadline) {
+                Some(slot) => slot,
+                None => return Err(value),
+            };
+        }
+        let id = match std::mem::replace(&mut slot.state, State::Empty) {
+            State::Waiting { id, value: theirs } => {
+                slot.state = State::Done { id, value };
+                self.changed.notify_all();
+                return Ok(theirs);
+            }
+            _ => {
+                let id = slot.next_id;
+                slot.next_id += 1;
+                slot.state = State::Waiting { id, value };
+                id
+            }
+        };
+        loop {
+            if matches!(slot.state, State::Done { id: done, .. } if done == id) {
+                let State::Done { value, .. } = std::mem::replace(&mut slot.state, State::Empty)
+                else {
+                    unreachable!()
+                };
+                self.changed.notify_all();
+                return Ok(value);
+            }
+            slot = match self.wait(slot, deadline) {
+                Some(slot) => slot,
+                None => {
+                    // Vencido, pero hay que volver a mirar con el lock: la pareja pudo llegar justo
+                    let mut slot = self.slot.lock().unwrap();
+                    return match std::mem::replace(&mut slot.state, State::Empty) {
+                        State::Done { id: done, value } if done == id => Ok(value),
+                        State::Waiting { id: waiting, value } if waiting == id => Err(value),
+                        // Sólo nosotros sacamos nuestra oferta o su respuesta
+                        _ => unreachable!("nuestra oferta desapareció"),
+                    };
+                }
+            };
+        }
+    }
+
+    // None si venció el plazo. En ese caso suelta el lock: quien llama lo vuelve a tomar
+    fn wait<'a>(
+        &self,
+        slot: std::sync::MutexGuard<'a, Slot<T>>,
+        deadline: Option<Instant>,
+    ) -> Option<std::sync::MutexGuard<'a, Slot<T>>> {
+        match deadline {
+            None => Some(self.changed.wait(slot).unwrap()),
+            Some(deadline) => {
+                let left = deadline.checked_duration_since(Instant::now())?;
+                let (slot, result) = self.changed.wait_timeout(slot, left).unwrap();
+                if result.timed_out() && Instant::now() >= deadline {
+                    None
+                } else {
+                    Some(slot)
+                }
+            }
+        }
+    }
+}
+
+impl<T> Default for Exchanger<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Rendezvous;
+
+impl Rendezvous {
+    // Los dos extremos de un handshake: lo que uno manda lo recibe el otro
+    pub fn pair<T: Send>() -> (Peer<T>, Peer<T>) {
+        let (a_tx, b_rx) = sync_channel(1);
+        let (b_tx, a_rx) = sync_channel(1);
+        (
+            Peer {
+                outbox: a_tx,
+                inbox: a_rx,
+                broken: false,
+            },
+            Peer {
+                outbox: b_tx,
+                inbox: b_rx,
+                broken: false,
+            },
+        )
+    }
+}
+
+pub struct Peer<T> {
+    outbox: SyncSender<T>,
+    inbox: Receiver<T>,
+    // Un timeout deja nuestro valor en el canal sin respuesta: los dos lados quedaron desfasados
+    broken: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RendezvousError {
+    // El otro extremo ya no existe
+    Disconnected,
+    // El otro no llegó a tiempo. El par queda roto: hay que soltarlo
+    Timeout,
+    Broken,
+}
+
+impl fmt::Display for RendezvousError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendezvousError::Disconnected => write!(f, "el otro extremo se desconectó"),
+            RendezvousError::Timeout => write!(f, "el otro extremo no llegó a tiempo"),
+            RendezvousError::Broken => write!(f, "el handshake quedó desfasado por un timeout"),
+        }
+    }
+}
+
+impl std::error::Error for RendezvousError {}
+
+impl<T: Send> Peer<T> {
+    // Manda primero y después espera: el buffer de un lugar hace que el orden no importe
+    pub fn exchange(&mut self, value: T) -> Result<T, RendezvousError> {
+        self.send(value)?;
+        self.inbox.recv().map_err(|_| RendezvousError::Disconnected)
+    }
+
+    pub fn exchange_timeout(&mut self, value: T, timeout: Duration) -> Result<T, RendezvousError> {
+        self.send(value)?;
+        match self.inbox.recv_timeout(timeout) {
+            Ok(theirs) => Ok(theirs),
+            Err(RecvTimeoutError::Disconnected) => Err(RendezvousError::Disconnected),
+            Err(RecvTimeoutError::Timeout) => {
+                self.broken = true;
+                Err(RendezvousError::Timeout)
+            }
+        }
+    }
+
+    fn send(&self, value: T) -> Result<(), RendezvousError> {
+        if self.broken {
+            return Err(RendezvousError::Broken);
+        }
+        self.outbox
+            .send(value)
+            .map_err(|_| RendezvousError::Disconnected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    const ROUNDS: usize = 10_000;
+
+    // Corre `f` en otro hilo y falla en vez de colgarse si no termina en 10 segundos
+    fn within_deadline<F: FnOnce() + Send + 'static>(f: F) {
+        let (done_tx, done_rx) = channel();
+        thread::spawn(move || {
+            f();
+            done_tx.send(()).unwrap();
+        });
+        done_rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("deadlock: los hilos no terminaron");
+    }
+
+    // 1. Dos hilos intercambian sus valores
+    #[test]
+    fn exchanger_swaps_values() {
+        let exchanger = Arc::new(Exchanger::new());
+        let other = {
+            let exchanger = Arc::clone(&exchanger);
+            thread::spawn(move || exchanger.exchange("de B"))
+        };
+        assert_eq!(exchanger.exchange("de A"), "de B");
+        assert_eq!(other.join().unwrap(), "de A");
+    }
+
+    // 2. Si no llega nadie, el valor vuelve y el exchanger queda listo para otra pareja
+    #[test]
+    fn exchanger_timeout_returns_our_value() {
+        let exchanger = Arc::new(Exchanger::new());
+        assert_eq!(
+            exchanger.exchange_timeout(1, Duration::from_millis(20)),
+            Err(1)
+        );
+        let other = {
+            let exchanger = Arc::clone(&exchanger);
+            thread::spawn(move || exchanger.exchange(2))
+        };
+        assert_eq!(exchanger.exchange_timeout(3, Duration::from_secs(5)), Ok(2));
+        assert_eq!(other.join().unwrap(), 3);
+    }
+
+    // 3. Con muchos hilos, cada uno recibe el valor de su pareja y la pareja el suyo
+    #[test]
+    fn exchanger_pairs_many_threads() {
+        let exchanger = Arc::new(Exchanger::new());
+        let handles: Vec<_> = (0..16)
+            .map(|i| {
+                let exchanger = Arc::clone(&exchanger);
+                thread::spawn(move || (i, exchanger.exchange(i)))
+            })
+            .collect();
+        let mut partner = [usize::MAX; 16];
+        for h in handles {
+            let (mine, theirs) = h.join().unwrap();
+            partner[mine] = theirs;
+        }
+        for (i, &p) in partner.iter().enumerate() {
+            assert_ne!(p, i);
+            assert_eq!(partner[p], i);
+        }
+    }
+
+    // 4. Los dos esperan al otro todo el tiempo, en miles de rondas, y nunca se traban
+    #[test]
+    fn mutually_waiting_peers_never_deadlock() {
+        within_deadline(|| {
+            let exchanger = Arc::new(Exchanger::new());
+            let b = {
+                let exchanger = Arc::clone(&exchanger);
+                thread::spawn(move || {
+                    for i in 0..ROUNDS {
+                        assert_eq!(exchanger.exchange(("B", i)), ("A", i));
+                    }
+                })
+            };
+            for i in 0..ROUNDS {
+                assert_eq!(exchanger.exchange(("A", i)), ("B", i));
+            }
+            b.join().unwrap();
+        });
+        within_deadline(|| {
+            let (mut a, mut b) = Rendezvous::pair();
+            let b = thread::spawn(move || {
+                for i in 0..ROUNDS {
+                    assert_eq!(b.exchange(("B", i)), Ok(("A", i)));
+                }
+            });
+            for i in 0..ROUNDS {
+                assert_eq!(a.exchange(("A", i)), Ok(("B", i)));
+            }
+            b.join().unwrap();
+        });
+    }
+
+    // 5. Timeout y desconexión del handshake por canales
+    #[test]
+    fn rendezvous_timeout_and_disconnect() {
+        let (mut a, b) = Rendezvous::pair::<i32>();
+        assert_eq!(
+            a.exchange_timeout(1, Duration::from_millis(20)),
+            Err(RendezvousError::Timeout)
+        );
+        assert_eq!(a.exchange(2), Err(RendezvousError::Broken));
+        drop(b);
+        let (mut a, b) = Rendezvous::pair::<i32>();
+        drop(b);
+        assert_eq!(a.exchange(1), Err(RendezvousError::Disconnected));
+    }
+}
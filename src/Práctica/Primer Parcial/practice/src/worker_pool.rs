@@ -0,0 +1,297 @@
+/*
+3. **Pool de trabajadores (Worker Pool)**
+   - **Descripción:** Un hilo “dispatcher” envía tareas por un canal a un grupo de K hilos trabajadores que las reciben, las ejecutan y, opcionalmente, responden por otro canal de resultados.
+   - **Retos conceptuales:**
+     - Balanceo de carga entre trabajadores.
+     - Manejo de respuestas por un canal distinto sin bloquear al dispatcher.
+ */
+
+// Balanceo: cada worker avisa por `ready` cuando está libre y el dispatcher le pasa la próxima
+// tarea sólo a alguien que avisó. Un worker ocupado con una tarea larga no acumula más.
+//
+// Respuestas: las manda el worker, nunca el dispatcher, y siempre por canales sin límite
+// (`mpsc::channel`), así que nadie se bloquea esperando a que el que pidió el resultado lo lea.
+use crate::channels::panic_message;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+pub struct WorkerPool {
+    jobs: Option<Sender<Job>>,
+    dispatcher: Option<JoinHandle<()>>,
+    workers: Vec<JoinHandle<()>>,
+    received: Arc<[AtomicUsize]>,
+}
+
+impl WorkerPool {
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "hace falta al menos un worker");
+        let received: Arc<[AtomicUsize]> = (0..size).map(|_| AtomicUsize::new(0)).collect();
+        let (ready_tx, ready_rx) = channel::<usize>();
+        let mut worker_txs = Vec::with_capacity(size);
+        let mut workers = Vec::with_capacity(size);
+        for id in 0..size {
+            let (tx, rx) = channel::<Job>();
+            worker_txs.push(tx);
+            let ready = ready_tx.clone();
+            let received = Arc::clone(&received);
+            let worker = thread::Builder::new()
+                .name(format!("worker-{id}"))
+                .spawn(move || {
+                    // Si el dispatcher ya terminó el send falla y `recv` también: salimos
+                    while ready.send(id).is_ok() {
+                        let Ok(job) = rx.recv() else { break };
+                        received[id].fetch_add(1, Ordering::Relaxed);
+                        job();
+                    }
+                })
+                .expect("no se pudo crear el worker");
+            workers.push(worker);
+        }
+        drop(ready_tx);
+
+        let (jobs_tx, jobs_rx) = channel::<Job>();
+        let dispatcher = thread::spawn(move || {
+            // Sale cuando se cierra `jobs` y ya repartió todo lo pendiente; al soltar los
+            // emisores de cada worker, estos terminan después de su última tarea
+            for job in jobs_rx {
+                let Ok(worker) = ready_rx.recv() else { break };
+                if worker_txs[worker].send(job).is_err() {
+                    break;
+                }
+            }
+        });
+
+        WorkerPool {
+            jobs: Some(jobs_tx),
+            dispatcher: Some(dispatcher),
+            workers,
+            received,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    // Sin respuesta
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, f: F) {
+        self.send(Box::new(move || {
+            // Un panic en la tarea no se lleva puesto al worker
+            let _ = panic::catch_unwind(AssertUnwindSafe(f));
+        }));
+    }
+
+    // La respuesta vuelve por un canal propio de la tarea
+    pub fn submit<F, R>(&self, f: F) -> TaskHandle<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = channel();
+        self.send(Box::new(move || {
+            let _ = tx.send(panic::catch_unwind(AssertUnwindSafe(f)));
+        }));
+        TaskHandle { result: rx }
+    }
+
+    // La respuesta va a un canal que comparten varias tareas. Si ya nadie lo escucha, se descarta.
+    pub fn submit_to<F, R>(&self, f: F, results: Sender<R>)
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.execute(move || {
+            let _ = results.send(f());
+        });
+    }
+
+    // Cuántas tareas le tocaron a cada worker
+    pub fn tasks_per_worker(&self) -> Vec<usize> {
+        self.received
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    // Deja de aceptar tareas, espera a que se ejecuten las pendientes y a que terminen los hilos
+    pub fn shutdown(mut self) {
+        self.close();
+    }
+
+    fn send(&self, job: Job) {
+        self.jobs
+            .as_ref()
+            .expect("el pool ya está cerrado")
+            .send(job)
+            .expect("el dispatcher terminó");
+    }
+
+    fn close(&mut self) {
+        self.jobs = None;
+        if let Some(dispatcher) = self.dispatcher.take() {
+            let _ = dispatcher.join();
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+pub struct TaskHandle<R> {
+    result: Receiver<thread::Result<R>>,
+}
+
+#[derive(Debug)]
+pub enum TaskError {
+    Panicked(String),
+    // El pool se destruyó sin llegar a correr la tarea
+    Cancelled,
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::Panicked(message) => write!(f, "la tarea entró en pánico: {message}"),
+            TaskError::Cancelled => write!(f, "la tarea no llegó a ejecutarse"),
+        }
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+impl<R> TaskHandle<R> {
+    // Espera el resultado
+    pub fn join(self) -> Result<R, TaskError> {
+        match self.result.recv() {
+            Ok(result) => result.map_err(|panic| TaskError::Panicked(panic_message(panic))),
+            Err(_) => Err(TaskError::Cancelled),
+        }
+    }
+
+    // None si todavía no terminó
+    pub fn try_join(&self) -> Option<Result<R, TaskError>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result.map_err(|panic| TaskError::Panicked(panic_message(panic)))),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(TaskError::Cancelled)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // 1. Cada tarea devuelve su resultado por su handle
+    #[test]
+    fn submit_returns_each_result() {
+        let pool = WorkerPool::new(3);
+        let handles: Vec<_> = (0..20).map(|i| pool.submit(move || i * i)).collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(results, (0..20).map(|i| i * i).collect::<Vec<_>>());
+    }
+
+    // 2. Con tareas que tardan, las K reciben trabajo
+    #[test]
+    fn work_is_spread_across_all_workers() {
+        let pool = WorkerPool::new(4);
+        let handles: Vec<_> = (0..40)
+            .map(|_| {
+                pool.submit(|| {
+                    thread::sleep(Duration::from_millis(5));
+                    thread::current().name().unwrap().to_string()
+                })
+            })
+            .collect();
+        let mut names: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 4);
+        let tasks = pool.tasks_per_worker();
+        assert_eq!(tasks.iter().sum::<usize>(), 40);
+        assert!(tasks.iter().all(|&n| n > 0), "{tasks:?}");
+    }
+
+    // 3. Un worker trabado con una tarea larga no recibe más: las cortas van a los otros. La
+    // larga no termina hasta que terminaron todas las cortas.
+    #[test]
+    fn busy_worker_does_not_get_more_work() {
+        let pool = WorkerPool::new(3);
+        let (started, slow_started) = channel();
+        let (release, released) = channel::<()>();
+        let slow = pool.submit(move || {
+            started.send(()).unwrap();
+            let _ = released.recv();
+            thread::current().name().unwrap().to_string()
+        });
+        slow_started.recv().unwrap();
+        let (tx, rx) = channel();
+        for _ in 0..30 {
+            pool.submit_to(|| thread::current().name().unwrap().to_string(), tx.clone());
+        }
+        // Con plazo: si alguna le tocara al worker trabado, fallamos en vez de colgarnos
+        let quick: Vec<_> = (0..30)
+            .map(|_| rx.recv_timeout(Duration::from_secs(10)).unwrap())
+            .collect();
+        drop(release);
+        let slow = slow.join().unwrap();
+        assert!(!quick.contains(&slow));
+    }
+
+    // 4. Canal de resultados compartido: el dispatcher sigue repartiendo aunque nadie lea todavía
+    #[test]
+    fn results_channel_does_not_block_the_dispatcher() {
+        let pool = WorkerPool::new(2);
+        let (tx, rx) = channel();
+        for i in 0..1_000 {
+            pool.submit_to(move || i, tx.clone());
+        }
+        drop(tx);
+        // Recién leemos cuando ya se ejecutó todo
+        pool.shutdown();
+        let mut results: Vec<_> = rx.iter().collect();
+        results.sort_unstable();
+        assert_eq!(results, (0..1_000).collect::<Vec<_>>());
+    }
+
+    // 5. Una tarea que entra en pánico no tira abajo a su worker
+    #[test]
+    fn panicking_task_is_reported_and_worker_survives() {
+        let pool = WorkerPool::new(1);
+        let bad = pool.submit(|| -> i32 { panic!("tarea rota") });
+        match bad.join() {
+            Err(TaskError::Panicked(message)) => assert_eq!(message, "tarea rota"),
+            other => panic!("esperaba Panicked, llegó {other:?}"),
+        }
+        assert_eq!(pool.submit(|| 7).join().unwrap(), 7);
+    }
+
+    // 6. El shutdown espera a que se ejecuten todas las tareas ya enviadas
+    #[test]
+    fn shutdown_runs_pending_tasks() {
+        let pool = WorkerPool::new(2);
+        let done = Arc::new(AtomicUsize::new(0));
+        for _ in 0..10 {
+            let done = Arc::clone(&done);
+            pool.execute(move || {
+                thread::sleep(Duration::from_millis(5));
+                done.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        pool.shutdown();
+        assert_eq!(done.load(Ordering::Relaxed), 10);
+    }
+}
@@ -0,0 +1,45 @@
+// Pasa ITEMS enteros de un hilo a otro con el canal SPSC y con los dos canales de std.
+// Se corre con `cargo bench --bench spsc`.
+use non_blocking::spsc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ITEMS: usize = 1_000_000;
+const CAPACITY: usize = 1024;
+
+fn run<S, R>(send: S, recv: R) -> Duration
+where
+    S: FnOnce() + Send + 'static,
+    R: FnOnce(),
+{
+    let t0 = Instant::now();
+    let producer = thread::spawn(send);
+    recv();
+    producer.join().unwrap();
+    t0.elapsed()
+}
+
+fn main() {
+    let (tx, rx) = spsc::channel(CAPACITY);
+    let spsc = run(
+        move || (0..ITEMS).for_each(|i| tx.send(i).unwrap()),
+        move || assert_eq!(rx.iter().count(), ITEMS),
+    );
+
+    let (tx, rx) = mpsc::sync_channel(CAPACITY);
+    let sync_channel = run(
+        move || (0..ITEMS).for_each(|i| tx.send(i).unwrap()),
+        move || assert_eq!(rx.iter().count(), ITEMS),
+    );
+
+    let (tx, rx) = mpsc::channel();
+    let channel = run(
+        move || (0..ITEMS).for_each(|i| tx.send(i).unwrap()),
+        move || assert_eq!(rx.iter().count(), ITEMS),
+    );
+
+    println!("{:>16} {:>14?}", "spsc", spsc);
+    println!("{:>16} {:>14?}", "mpsc::sync", sync_channel);
+    println!("{:>16} {:>14?}", "mpsc", channel);
+}
@@ -0,0 +1,62 @@
+// Compara las tres pilas con 1 a 32 hilos haciendo pares push/pop sobre la misma pila.
+// Se corre con `cargo bench --bench stacks`. Las pilas lock-free sólo existen con punteros de
+// 64 bits.
+#![cfg_attr(not(target_pointer_width = "64"), allow(dead_code, unused_imports))]
+#[cfg(target_pointer_width = "64")]
+use non_blocking::elimination::EliminationBackoffStack;
+#[cfg(target_pointer_width = "64")]
+use non_blocking::stack::NonBlockingStack;
+use non_blocking::stack::{BlockingStack, Stack};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const OPS_PER_THREAD: usize = 20_000;
+const THREADS: [usize; 6] = [1, 2, 4, 8, 16, 32];
+
+fn run<S: Stack<usize> + Send + Sync + 'static>(stack: S, threads: usize) -> Duration {
+    let stack = Arc::new(stack);
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|t| {
+            let s = Arc::clone(&stack);
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                barrier.wait();
+                for i in 0..OPS_PER_THREAD {
+                    s.push(t * OPS_PER_THREAD + i);
+                    s.pop();
+                }
+            })
+        })
+        .collect();
+    barrier.wait();
+    let t0 = Instant::now();
+    for h in handles {
+        h.join().unwrap();
+    }
+    t0.elapsed()
+}
+
+#[cfg(target_pointer_width = "64")]
+fn main() {
+    println!(
+        "{:>7} {:>14} {:>14} {:>14}",
+        "hilos", "mutex", "lock-free", "eliminación"
+    );
+    for threads in THREADS {
+        let capacity = threads * OPS_PER_THREAD;
+        let blocking = run(BlockingStack::new(capacity), threads);
+        let non_blocking = run(NonBlockingStack::new(), threads);
+        let elimination = run(EliminationBackoffStack::new(), threads);
+        println!(
+            "{:>7} {:>14?} {:>14?} {:>14?}",
+            threads, blocking, non_blocking, elimination
+        );
+    }
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+fn main() {
+    println!("las pilas lock-free necesitan punteros de 64 bits");
+}
@@ -1,34 +1,36 @@
+use crate::backoff::{Backoff, TruncatedExponentialBackoff};
+use std::marker::PhantomData;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::thread;
-use std::time::Duration;
 
-struct BackoffCounter {
+// El estado del backoff vive en cada llamada a increment, no en el contador: así un hilo que
+// falla mucho no hace dormir de más a los demás
+struct BackoffCounter<B: Backoff = TruncatedExponentialBackoff> {
     value: AtomicUsize,
-    sleep_time: AtomicUsize,
+    _backoff: PhantomData<fn() -> B>,
 }
 
 const ACQ: Ordering = Ordering::Acquire;
-impl BackoffCounter {
+impl<B: Backoff> BackoffCounter<B> {
     pub fn new(initial: usize) -> Self {
         let value = AtomicUsize::new(initial);
         BackoffCounter {
             value,
-            sleep_time: AtomicUsize::new(1),
+            _backoff: PhantomData,
         }
     }
 
     pub fn increment(&self) {
-        let val = self.value.load(Ordering::Acquire);
-        let sleep = self.sleep_time.load(Ordering::Acquire);
-        if self
-            .value
-            .compare_exchange_weak(val, val + 1, Ordering::AcqRel, Ordering::Acquire)
-            .is_err()
-        {
-            thread::sleep(Duration::from_micros(sleep as u64));
-            if sleep < 128 {
-                self.sleep_time.fetch_add(sleep, Ordering::AcqRel);
+        let mut backoff = B::default();
+        loop {
+            let val = self.value.load(Ordering::Acquire);
+            if self
+                .value
+                .compare_exchange_weak(val, val + 1, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return;
             }
+            backoff.backoff();
         }
     }
 
@@ -40,8 +42,10 @@ impl BackoffCounter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backoff::{ExponentialJitterBackoff, SpinBackoff, YieldBackoff};
     use std::sync::Arc;
-    use std::time::Instant;
+    use std::thread;
+    use std::time::{Duration, Instant};
 
     struct NoBackoffCounter {
         value: AtomicUsize,
@@ -62,7 +66,6 @@ mod tests {
                     .compare_exchange_weak(cur, cur + 1, Ordering::AcqRel, Ordering::Acquire)
                     .is_ok()
                 {
-
                     break;
                 }
 
@@ -76,17 +79,12 @@ mod tests {
         }
     }
 
-    // Hay muy pocos que fallan, me da 159903 en lugar de 16k
-    #[test]
-    fn backoff_vs_no_backoff_performance_and_correctness() {
-        let threads = 16;
-        let increments = 10_000;
-
-        let backoff = Arc::new(BackoffCounter::new(0));
+    fn run_backoff_counter<B: Backoff + 'static>(threads: usize, increments: usize) -> Duration {
+        let counter = Arc::new(BackoffCounter::<B>::new(0));
         let t0 = Instant::now();
         let handlers: Vec<_> = (0..threads)
             .map(|_| {
-                let c = Arc::clone(&backoff);
+                let c = Arc::clone(&counter);
                 thread::spawn(move || {
                     for _ in 0..increments {
                         c.increment();
@@ -97,8 +95,17 @@ mod tests {
         for h in handlers {
             h.join().unwrap();
         }
-        let dur_backoff = t0.elapsed();
-        assert_eq!(backoff.get(), threads * increments);
+        let elapsed = t0.elapsed();
+        assert_eq!(counter.get(), threads * increments);
+        elapsed
+    }
+
+    #[test]
+    fn backoff_vs_no_backoff_performance_and_correctness() {
+        let threads = 16;
+        let increments = 10_000;
+
+        let dur_backoff = run_backoff_counter::<TruncatedExponentialBackoff>(threads, increments);
         println!("tiempo con backoff: {:?}", dur_backoff);
 
         let noback = Arc::new(NoBackoffCounter::new(0));
@@ -120,4 +127,17 @@ mod tests {
         assert_eq!(noback.get(), threads * increments);
         println!("tiempo sin backoff: {:?}", dur_noback);
     }
+
+    #[test]
+    fn every_strategy_counts_every_increment() {
+        let threads = 16;
+        let increments = 5_000;
+        let spin = run_backoff_counter::<SpinBackoff>(threads, increments);
+        let yield_now = run_backoff_counter::<YieldBackoff>(threads, increments);
+        let jitter = run_backoff_counter::<ExponentialJitterBackoff>(threads, increments);
+        let truncated = run_backoff_counter::<TruncatedExponentialBackoff>(threads, increments);
+        println!(
+            "spin: {spin:?}, yield: {yield_now:?}, jitter: {jitter:?}, truncada: {truncated:?}"
+        );
+    }
 }
@@ -0,0 +1,134 @@
+// Estrategias de espera después de un CAS fallido.
+//
+// Cada hilo arma su propio `Backoff` (no hay estado compartido), lo llama después de cada
+// intento fallido y lo descarta cuando la operación tiene éxito.
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::hint;
+use std::thread;
+use std::time::Duration;
+
+pub trait Backoff: Default {
+    fn backoff(&mut self);
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// Espera activa: duplica las vueltas de spin_loop hasta 2^SPIN_LIMIT
+const SPIN_LIMIT: u32 = 6;
+
+#[derive(Default)]
+pub struct SpinBackoff {
+    step: u32,
+}
+
+impl Backoff for SpinBackoff {
+    fn backoff(&mut self) {
+        for _ in 0..1 << self.step {
+            hint::spin_loop();
+        }
+        if self.step < SPIN_LIMIT {
+            self.step += 1;
+        }
+    }
+}
+
+// Le cede el procesador al scheduler, sin dormir
+#[derive(Default)]
+pub struct YieldBackoff;
+
+impl Backoff for YieldBackoff {
+    fn backoff(&mut self) {
+        thread::yield_now();
+    }
+}
+
+const MIN_SLEEP_MICROS: u64 = 1;
+const MAX_SLEEP_MICROS: u64 = 128;
+
+// 1µs, 2µs, 4µs... hasta el tope, como pide el ejercicio 2.3
+pub struct TruncatedExponentialBackoff {
+    sleep_micros: u64,
+}
+
+impl Default for TruncatedExponentialBackoff {
+    fn default() -> Self {
+        TruncatedExponentialBackoff {
+            sleep_micros: MIN_SLEEP_MICROS,
+        }
+    }
+}
+
+impl Backoff for TruncatedExponentialBackoff {
+    fn backoff(&mut self) {
+        thread::sleep(Duration::from_micros(self.sleep_micros));
+        self.sleep_micros = (self.sleep_micros * 2).min(MAX_SLEEP_MICROS);
+    }
+}
+
+// Igual que la truncada, pero duerme un valor al azar entre 0 y el tope actual ("full jitter")
+// para que los hilos que fallaron juntos no vuelvan a chocar juntos
+pub struct ExponentialJitterBackoff {
+    limit_micros: u64,
+}
+
+impl Default for ExponentialJitterBackoff {
+    fn default() -> Self {
+        ExponentialJitterBackoff {
+            limit_micros: MIN_SLEEP_MICROS,
+        }
+    }
+}
+
+impl Backoff for ExponentialJitterBackoff {
+    fn backoff(&mut self) {
+        let sleep = random_below(self.limit_micros + 1);
+        thread::sleep(Duration::from_micros(sleep));
+        self.limit_micros = (self.limit_micros * 2).min(MAX_SLEEP_MICROS);
+    }
+}
+
+thread_local! {
+    // La semilla sale de las claves aleatorias de RandomState, distintas en cada hilo
+    static RNG: Cell<u64> = Cell::new(RandomState::new().hash_one(thread::current().id()) | 1);
+}
+
+// xorshift64 por hilo: alcanza para repartir esperas o casilleros sin coordinar con nadie
+pub(crate) fn random_below(n: u64) -> u64 {
+    RNG.with(|rng| {
+        let mut x = rng.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        rng.set(x);
+        x % n
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_backoff_stops_growing_at_the_cap() {
+        let mut backoff = TruncatedExponentialBackoff::default();
+        for _ in 0..10 {
+            backoff.backoff();
+        }
+        assert_eq!(backoff.sleep_micros, MAX_SLEEP_MICROS);
+        backoff.reset();
+        assert_eq!(backoff.sleep_micros, MIN_SLEEP_MICROS);
+    }
+
+    #[test]
+    fn jitter_limit_stops_growing_at_the_cap() {
+        let mut backoff = ExponentialJitterBackoff::default();
+        for _ in 0..10 {
+            backoff.backoff();
+        }
+        assert_eq!(backoff.limit_micros, MAX_SLEEP_MICROS);
+    }
+}
@@ -0,0 +1,209 @@
+// Pila con "elimination backoff" (Hendler, Shavit & Yerushalmi).
+//
+// Un push y un pop concurrentes se cancelan entre sí: si el push le pasa su nodo directamente al
+// pop, la pila queda igual que si hubieran ocurrido uno detrás del otro. Entonces, cuando un hilo
+// pierde el CAS sobre el head, en lugar de reintentar enseguida visita un casillero al azar del
+// arreglo de eliminación y espera un rato a que aparezca alguien con la operación contraria.
+// Sólo si nadie aparece vuelve a intentar sobre la pila compartida.
+use crate::backoff::random_below;
+use crate::stack::{Node, NonBlockingStack, Stack};
+use crate::tagged::{AtomicTaggedPtr, TaggedPtr};
+use std::hint;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+const ACQ: Ordering = Ordering::Acquire;
+const ACQ_REL: Ordering = Ordering::AcqRel;
+
+// Vueltas que un hilo espera en un casillero antes de volver a la pila
+const DEFAULT_SPINS: usize = 128;
+
+pub struct EliminationBackoffStack<T> {
+    stack: NonBlockingStack<T>,
+    // Un push deja su nodo en un casillero vacío y un pop se lo lleva dejándolo otra vez en null.
+    // El tag evita que el push confunda su nodo con otro que quedó en la misma dirección.
+    slots: Box<[AtomicTaggedPtr<Node<T>>]>,
+    spins: usize,
+}
+
+impl<T> EliminationBackoffStack<T> {
+    pub fn new() -> Self {
+        let slots = thread::available_parallelism().map_or(4, |n| n.get());
+        Self::with_slots(slots, DEFAULT_SPINS)
+    }
+
+    pub fn with_slots(slots: usize, spins: usize) -> Self {
+        assert!(slots > 0, "hace falta al menos un casillero");
+        EliminationBackoffStack {
+            stack: NonBlockingStack::new(),
+            slots: (0..slots)
+                .map(|_| AtomicTaggedPtr::new(TaggedPtr::null()))
+                .collect(),
+            spins,
+        }
+    }
+
+    // Ok si un pop se llevó el nodo; si no, lo devuelve para reintentar sobre la pila
+    fn eliminate_push(&self, node: Box<Node<T>>) -> Result<(), Box<Node<T>>> {
+        let slot = &self.slots[random_below(self.slots.len() as u64) as usize];
+        let empty = slot.load(ACQ);
+        if !empty.is_null() {
+            return Err(node);
+        }
+        let node = Box::into_raw(node);
+        let offered = empty.next(node);
+        if slot.compare_exchange(empty, offered, ACQ_REL, ACQ).is_err() {
+            return Err(unsafe { Box::from_raw(node) });
+        }
+        for _ in 0..self.spins {
+            if slot.load(ACQ) != offered {
+                return Ok(());
+            }
+            hint::spin_loop();
+        }
+        // Nadie vino: retiramos la oferta. Si el CAS falla es porque un pop llegó justo a tiempo
+        match slot.compare_exchange(offered, offered.next(std::ptr::null_mut()), ACQ_REL, ACQ) {
+            Ok(_) => Err(unsafe { Box::from_raw(node) }),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn eliminate_pop(&self) -> Option<T> {
+        let slot = &self.slots[random_below(self.slots.len() as u64) as usize];
+        for _ in 0..self.spins {
+            let offered = slot.load(ACQ);
+            if !offered.is_null()
+                && slot
+                    .compare_exchange(offered, offered.next(std::ptr::null_mut()), ACQ_REL, ACQ)
+                    .is_ok()
+            {
+                // El push ya no toca el nodo después de ofrecerlo: ahora es nuestro
+                let node = unsafe { Box::from_raw(offered.ptr()) };
+                return Some(node.into_item());
+            }
+            hint::spin_loop();
+        }
+        None
+    }
+}
+
+impl<T> Default for EliminationBackoffStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Stack<T> for EliminationBackoffStack<T> {
+    fn push(&self, value: T) {
+        let mut node = Box::new(Node::new(value));
+        loop {
+            node = match self.stack.try_push_node(node) {
+                Ok(()) => return,
+                Err(node) => node,
+            };
+            node = match self.eliminate_push(node) {
+                Ok(()) => return,
+                Err(node) => node,
+            };
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        loop {
+            if let Ok(item) = self.stack.try_pop_once() {
+                return item;
+            }
+            if let Some(item) = self.eliminate_pop() {
+                return Some(item);
+            }
+        }
+    }
+}
+
+impl<T> Drop for EliminationBackoffStack<T> {
+    fn drop(&mut self) {
+        // Cada push retira su oferta antes de volver, así que esto es sólo una red de seguridad
+        for slot in self.slots.iter_mut() {
+            let offered = slot.get_mut();
+            if !offered.is_null() {
+                drop(unsafe { Box::from_raw(offered.ptr()) }.into_item());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+
+    #[test]
+    fn behaves_like_a_stack_single_thread() {
+        let stack = EliminationBackoffStack::new();
+        assert_eq!(stack.try_pop(), None);
+        for i in 0..10 {
+            stack.push(i);
+        }
+        for i in (0..10).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn push_and_pop_meet_in_the_elimination_array() {
+        // Un solo casillero y mucha espera para que se encuentren seguro
+        let stack = Arc::new(EliminationBackoffStack::with_slots(1, 1_000_000));
+        let pusher = {
+            let s = Arc::clone(&stack);
+            thread::spawn(move || s.eliminate_push(Box::new(Node::new(42))).is_ok())
+        };
+        let popped = loop {
+            if let Some(v) = stack.eliminate_pop() {
+                break v;
+            }
+        };
+        assert_eq!(popped, 42);
+        assert!(pusher.join().unwrap());
+        assert_eq!(stack.try_pop(), None);
+    }
+
+    #[test]
+    fn withdrawn_offer_goes_back_to_the_caller() {
+        let stack: EliminationBackoffStack<i32> = EliminationBackoffStack::with_slots(1, 10);
+        let node = stack.eliminate_push(Box::new(Node::new(7))).unwrap_err();
+        assert_eq!(node.into_item(), 7);
+        assert!(stack.slots[0].load(ACQ).is_null());
+    }
+
+    #[test]
+    fn concurrent_pairs_lose_and_duplicate_nothing() {
+        let threads = 8;
+        let per_thread = 5_000;
+        let stack = Arc::new(EliminationBackoffStack::with_slots(2, 64));
+        let barrier = Arc::new(Barrier::new(threads));
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                let s = Arc::clone(&stack);
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    let mut seen = Vec::with_capacity(per_thread);
+                    for i in 0..per_thread {
+                        s.push(t * per_thread + i);
+                        // Siempre hay al menos un elemento nuestro pendiente, así que no es None
+                        seen.push(s.pop().unwrap());
+                    }
+                    seen
+                })
+            })
+            .collect();
+        let mut all: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        all.sort_unstable();
+        assert_eq!(all, (0..threads * per_thread).collect::<Vec<_>>());
+        assert_eq!(stack.try_pop(), None);
+    }
+}
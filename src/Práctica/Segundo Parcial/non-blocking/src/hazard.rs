@@ -0,0 +1,290 @@
+// Hazard pointers (Michael, 2004) para liberar memoria en estructuras lock-free.
+//
+// Antes de desreferenciar un nodo compartido, el hilo lo "publica" en un registro de hazard.
+// Quien saca el nodo de la estructura no lo libera: lo "retira", y el nodo recién se libera
+// cuando ningún registro lo está publicando. Así se evita el use-after-free (y el ABA que
+// aparece cuando el allocator reutiliza la dirección liberada).
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+const SEQ: Ordering = Ordering::SeqCst;
+
+// Cantidad mínima de nodos retirados antes de intentar liberar.
+const SCAN_THRESHOLD: usize = 64;
+
+struct HazardRecord {
+    active: AtomicBool,
+    ptr: AtomicPtr<()>,
+    next: AtomicPtr<HazardRecord>,
+}
+
+struct Retired {
+    ptr: *mut (),
+    deleter: unsafe fn(*mut ()),
+    next: *mut Retired,
+}
+
+pub struct HazardDomain {
+    records: AtomicPtr<HazardRecord>,
+    record_count: AtomicUsize,
+    retired: AtomicPtr<Retired>,
+    retired_count: AtomicUsize,
+}
+
+impl HazardDomain {
+    pub const fn new() -> Self {
+        HazardDomain {
+            records: AtomicPtr::new(null_mut()),
+            record_count: AtomicUsize::new(0),
+            retired: AtomicPtr::new(null_mut()),
+            retired_count: AtomicUsize::new(0),
+        }
+    }
+
+    // Reutiliza un registro libre o agrega uno nuevo a la lista (los registros nunca se sacan).
+    pub fn acquire(&self) -> HazardGuard<'_> {
+        let mut cur = self.records.load(SEQ);
+        while !cur.is_null() {
+            let record = unsafe { &*cur };
+            if !record.active.load(Ordering::Relaxed)
+                && record
+                    .active
+                    .compare_exchange(false, true, SEQ, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return HazardGuard { record };
+            }
+            cur = record.next.load(SEQ);
+        }
+
+        let new_record = Box::into_raw(Box::new(HazardRecord {
+            active: AtomicBool::new(true),
+            ptr: AtomicPtr::new(null_mut()),
+            next: AtomicPtr::new(null_mut()),
+        }));
+        loop {
+            let head = self.records.load(SEQ);
+            unsafe { (*new_record).next.store(head, Ordering::Relaxed) };
+            if self
+                .records
+                .compare_exchange(head, new_record, SEQ, SEQ)
+                .is_ok()
+            {
+                self.record_count.fetch_add(1, SEQ);
+                return HazardGuard {
+                    record: unsafe { &*new_record },
+                };
+            }
+        }
+    }
+
+    /// Difiere el `drop` de `ptr` hasta que ningún hazard lo publique.
+    ///
+    /// # Safety
+    /// `ptr` tiene que venir de `Box::into_raw`, ya no puede ser alcanzable desde la
+    /// estructura y no se puede retirar dos veces.
+    pub unsafe fn retire<T>(&self, ptr: *mut T) {
+        unsafe fn drop_box<T>(ptr: *mut ()) {
+            drop(unsafe { Box::from_raw(ptr as *mut T) });
+        }
+        let node = Box::into_raw(Box::new(Retired {
+            ptr: ptr as *mut (),
+            deleter: drop_box::<T>,
+            next: null_mut(),
+        }));
+        // Contamos antes de publicarlo: un scan concurrente lo puede liberar enseguida
+        let count = self.retired_count.fetch_add(1, SEQ) + 1;
+        self.push_retired(node);
+
+        if count >= SCAN_THRESHOLD.max(2 * self.record_count.load(SEQ)) {
+            self.scan();
+        }
+    }
+
+    // Libera todos los nodos retirados que no estén protegidos por algún hazard.
+    pub fn scan(&self) {
+        let mut cur = self.retired.swap(null_mut(), SEQ);
+        if cur.is_null() {
+            return;
+        }
+
+        let mut hazards = Vec::with_capacity(self.record_count.load(SEQ));
+        let mut record = self.records.load(SEQ);
+        while !record.is_null() {
+            let r = unsafe { &*record };
+            let p = r.ptr.load(SEQ);
+            if !p.is_null() {
+                hazards.push(p);
+            }
+            record = r.next.load(SEQ);
+        }
+
+        while !cur.is_null() {
+            let next = unsafe { (*cur).next };
+            if hazards.contains(&unsafe { (*cur).ptr }) {
+                self.push_retired(cur);
+            } else {
+                let retired = unsafe { Box::from_raw(cur) };
+                unsafe { (retired.deleter)(retired.ptr) };
+                self.retired_count.fetch_sub(1, SEQ);
+            }
+            cur = next;
+        }
+    }
+
+    fn push_retired(&self, node: *mut Retired) {
+        loop {
+            let head = self.retired.load(SEQ);
+            unsafe { (*node).next = head };
+            if self.retired.compare_exchange(head, node, SEQ, SEQ).is_ok() {
+                return;
+            }
+        }
+    }
+}
+
+impl Default for HazardDomain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for HazardDomain {
+    fn drop(&mut self) {
+        // Con &mut self no quedan guards vivos, así que todo lo retirado se puede liberar.
+        let mut cur = *self.retired.get_mut();
+        while !cur.is_null() {
+            let retired = unsafe { Box::from_raw(cur) };
+            unsafe { (retired.deleter)(retired.ptr) };
+            cur = retired.next;
+        }
+        let mut record = *self.records.get_mut();
+        while !record.is_null() {
+            let r = unsafe { Box::from_raw(record) };
+            record = r.next.load(Ordering::Relaxed);
+        }
+    }
+}
+
+pub struct HazardGuard<'d> {
+    record: &'d HazardRecord,
+}
+
+impl HazardGuard<'_> {
+    // Lee `src` y publica el puntero hasta que la publicación sea válida: si `src` no cambió
+    // después de publicarlo, ningún scan posterior lo puede liberar mientras siga publicado.
+    pub fn protect<T>(&self, src: &AtomicPtr<T>) -> *mut T {
+        let mut ptr = src.load(SEQ);
+        loop {
+            self.set(ptr);
+            let current = src.load(SEQ);
+            if current == ptr {
+                return ptr;
+            }
+            ptr = current;
+        }
+    }
+
+    pub fn set<T>(&self, ptr: *mut T) {
+        self.record.ptr.store(ptr as *mut (), SEQ);
+    }
+
+    pub fn clear(&self) {
+        self.record.ptr.store(null_mut(), SEQ);
+    }
+}
+
+impl Drop for HazardGuard<'_> {
+    fn drop(&mut self) {
+        self.clear();
+        self.record.active.store(false, SEQ);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, SEQ);
+        }
+    }
+
+    #[test]
+    fn protected_pointer_is_not_freed_until_cleared() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let domain = HazardDomain::new();
+        let shared = AtomicPtr::new(Box::into_raw(Box::new(DropCounter(drops.clone()))));
+
+        let guard = domain.acquire();
+        let ptr = guard.protect(&shared);
+        shared.store(null_mut(), SEQ);
+        unsafe { domain.retire(ptr) };
+        domain.scan();
+        assert_eq!(drops.load(SEQ), 0);
+        assert_eq!(unsafe { (*ptr).0.load(SEQ) }, 0);
+
+        guard.clear();
+        domain.scan();
+        assert_eq!(drops.load(SEQ), 1);
+    }
+
+    #[test]
+    fn records_are_reused_after_guard_drop() {
+        let domain = HazardDomain::new();
+        drop(domain.acquire());
+        drop(domain.acquire());
+        let _a = domain.acquire();
+        let _b = domain.acquire();
+        assert_eq!(domain.record_count.load(SEQ), 2);
+    }
+
+    #[test]
+    fn drop_frees_every_retired_pointer() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let domain = HazardDomain::new();
+        let guard = domain.acquire();
+        for _ in 0..10 {
+            let ptr = Box::into_raw(Box::new(DropCounter(drops.clone())));
+            guard.set(ptr);
+            unsafe { domain.retire(ptr) };
+        }
+        drop(guard);
+        drop(domain);
+        assert_eq!(drops.load(SEQ), 10);
+    }
+
+    #[test]
+    fn concurrent_swap_and_read_never_sees_freed_value() {
+        let domain = Arc::new(HazardDomain::new());
+        let shared = Arc::new(AtomicPtr::new(Box::into_raw(Box::new(0usize))));
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let domain = Arc::clone(&domain);
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || {
+                    for i in 0..2_000 {
+                        if t % 2 == 0 {
+                            let new = Box::into_raw(Box::new(i));
+                            let old = shared.swap(new, SEQ);
+                            unsafe { domain.retire(old) };
+                        } else {
+                            let guard = domain.acquire();
+                            let ptr = guard.protect(&shared);
+                            assert!(unsafe { *ptr } < 2_000);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        drop(unsafe { Box::from_raw(shared.load(SEQ)) });
+    }
+}
@@ -0,0 +1,15 @@
+// El ejercicio del contador con backoff: sólo lo usan sus tests
+#[cfg(test)]
+mod atomic_counter;
+pub mod backoff;
+#[cfg(target_pointer_width = "64")]
+pub mod elimination;
+pub mod hazard;
+pub mod map;
+pub mod queue;
+pub mod ring_buffer;
+pub mod spsc;
+pub mod stack;
+// TaggedPtr guarda la generación en los 16 bits altos del puntero
+#[cfg(target_pointer_width = "64")]
+pub mod tagged;
@@ -1,6 +1,3 @@
-mod stack;
-mod atomic_counter;
-
 fn main() {
     println!("Hello, world!");
 }
@@ -0,0 +1,311 @@
+// Cola de Michael & Scott: head apunta siempre a un nodo "dummy" y el primer elemento real es
+// head.next. Los enqueue compiten sobre tail.next y los dequeue sobre head, así que productores
+// y consumidores casi no se pisan.
+use crate::hazard::HazardDomain;
+use std::marker::PhantomData;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+pub trait Queue<T> {
+    fn enqueue(&self, value: T);
+    fn dequeue(&self) -> Option<T>;
+}
+
+const ACQ: Ordering = Ordering::Acquire;
+const REL: Ordering = Ordering::Release;
+
+/// Sólo se comparte entre hilos si `T: Send`:
+///
+/// ```compile_fail
+/// use non_blocking::queue::NonBlockingQueue;
+/// fn assert_sync<S: Sync>() {}
+/// assert_sync::<NonBlockingQueue<std::rc::Rc<i32>>>();
+/// ```
+pub struct NonBlockingQueue<T> {
+    head: AtomicPtr<Node<T>>,
+    tail: AtomicPtr<Node<T>>,
+    size: AtomicUsize,
+    domain: HazardDomain,
+    _marker: PhantomData<T>,
+}
+
+// Los AtomicPtr solos harían a la cola Send + Sync para cualquier T. Los elementos pasan de un
+// hilo a otro pero nunca se comparten, así que alcanza con `T: Send`.
+unsafe impl<T: Send> Send for NonBlockingQueue<T> {}
+unsafe impl<T: Send> Sync for NonBlockingQueue<T> {}
+
+impl<T> NonBlockingQueue<T> {
+    pub fn new() -> Self {
+        let dummy = Box::into_raw(Box::new(Node::dummy()));
+        NonBlockingQueue {
+            head: AtomicPtr::new(dummy),
+            tail: AtomicPtr::new(dummy),
+            size: AtomicUsize::new(0),
+            domain: HazardDomain::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size.load(ACQ)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let guard = self.domain.acquire();
+        let head = guard.protect(&self.head);
+        unsafe { (*head).next.load(ACQ).is_null() }
+    }
+}
+
+impl<T> Default for NonBlockingQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Queue<T> for NonBlockingQueue<T> {
+    fn enqueue(&self, value: T) {
+        let new_node = Box::into_raw(Box::new(Node::new(value)));
+        let guard = self.domain.acquire();
+        // Se cuenta antes de enganchar el nodo: si no, un dequeue que ya lo ve podría restar
+        // primero y el contador daría la vuelta. El loop no se rinde, así que no hay que deshacerlo.
+        self.size.fetch_add(1, REL);
+        loop {
+            let tail = guard.protect(&self.tail);
+            let next = unsafe { (*tail).next.load(ACQ) };
+            if tail != self.tail.load(ACQ) {
+                continue;
+            }
+            if next.is_null() {
+                if unsafe { &(*tail).next }
+                    .compare_exchange(null_mut(), new_node, Ordering::AcqRel, ACQ)
+                    .is_ok()
+                {
+                    // Si falla, otro hilo ya avanzó el tail por nosotros
+                    let _ = self
+                        .tail
+                        .compare_exchange(tail, new_node, Ordering::AcqRel, ACQ);
+                    return;
+                }
+            } else {
+                // El tail quedó atrasado: lo ayudamos a avanzar antes de reintentar
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Ordering::AcqRel, ACQ);
+            }
+        }
+    }
+
+    fn dequeue(&self) -> Option<T> {
+        let head_guard = self.domain.acquire();
+        let next_guard = self.domain.acquire();
+        loop {
+            let head = head_guard.protect(&self.head);
+            let tail = self.tail.load(ACQ);
+            let next = unsafe { (*head).next.load(ACQ) };
+            next_guard.set(next);
+            // Si head no cambió, next sigue en la cola y el hazard que le pusimos es válido
+            if head != self.head.load(ACQ) {
+                continue;
+            }
+            if next.is_null() {
+                return None;
+            }
+            if head == tail {
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Ordering::AcqRel, ACQ);
+                continue;
+            }
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::AcqRel, ACQ)
+                .is_ok()
+            {
+                self.size.fetch_sub(1, REL);
+                // next pasa a ser el nuevo dummy: nos llevamos su item y liberamos el viejo
+                let item = unsafe { (*next).item.take() };
+                head_guard.clear();
+                next_guard.clear();
+                unsafe { self.domain.retire(head) };
+                return item;
+            }
+        }
+    }
+}
+
+impl<T> Drop for NonBlockingQueue<T> {
+    fn drop(&mut self) {
+        let mut cur = *self.head.get_mut();
+        while !cur.is_null() {
+            let node = unsafe { Box::from_raw(cur) };
+            cur = node.next.load(Ordering::Relaxed);
+        }
+    }
+}
+
+struct Node<T> {
+    item: Option<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn dummy() -> Self {
+        Node {
+            item: None,
+            next: AtomicPtr::new(null_mut()),
+        }
+    }
+    fn new(item: T) -> Self {
+        Node {
+            item: Some(item),
+            next: AtomicPtr::new(null_mut()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    macro_rules! queue_tests {
+        ($mod_name:ident, $queue_expr:expr) => {
+            mod $mod_name {
+                use super::*;
+
+                #[test]
+                fn empty_queue_returns_none() {
+                    let queue = $queue_expr;
+                    assert_eq!(queue.dequeue(), None);
+                    queue.enqueue(1);
+                    assert_eq!(queue.dequeue(), Some(1));
+                    assert_eq!(queue.dequeue(), None);
+                    assert_eq!(queue.dequeue(), None);
+                }
+
+                #[test]
+                fn single_thread_fifo_order() {
+                    let queue = $queue_expr;
+                    for i in 0..100 {
+                        queue.enqueue(i);
+                    }
+                    for i in 0..100 {
+                        assert_eq!(queue.dequeue(), Some(i));
+                    }
+                    assert_eq!(queue.dequeue(), None);
+                }
+
+                // Exercise 2.2: un productor, varios consumidores hasta vaciar la cola
+                #[test]
+                fn single_producer_many_consumers() {
+                    let queue = $queue_expr;
+                    let done = Arc::new(AtomicBool::new(false));
+                    let producer = {
+                        let q = Arc::clone(&queue);
+                        let done = Arc::clone(&done);
+                        thread::spawn(move || {
+                            for i in 0..50_000 {
+                                q.enqueue(i);
+                            }
+                            done.store(true, REL);
+                        })
+                    };
+                    let consumers: Vec<_> = (0..4)
+                        .map(|_| {
+                            let q = Arc::clone(&queue);
+                            let done = Arc::clone(&done);
+                            thread::spawn(move || {
+                                let mut local = Vec::new();
+                                loop {
+                                    match q.dequeue() {
+                                        Some(v) => local.push(v),
+                                        None if done.load(ACQ) => match q.dequeue() {
+                                            Some(v) => local.push(v),
+                                            None => break,
+                                        },
+                                        None => thread::yield_now(),
+                                    }
+                                }
+                                local
+                            })
+                        })
+                        .collect();
+                    producer.join().unwrap();
+                    let mut all: Vec<i32> = consumers
+                        .into_iter()
+                        .flat_map(|h| h.join().unwrap())
+                        .collect();
+                    all.sort_unstable();
+                    assert_eq!(all, (0..50_000).collect::<Vec<_>>());
+                    assert_eq!(queue.len(), 0);
+                    assert!(queue.is_empty());
+                }
+
+                // Cada consumidor tiene que ver los valores de un mismo productor en orden
+                #[test]
+                fn multi_producer_multi_consumer_stress() {
+                    let queue = $queue_expr;
+                    let producers: Vec<_> = (0..4)
+                        .map(|p| {
+                            let q = Arc::clone(&queue);
+                            thread::spawn(move || {
+                                for i in 0..5_000 {
+                                    q.enqueue(p * 5_000 + i);
+                                }
+                            })
+                        })
+                        .collect();
+                    let consumers: Vec<_> = (0..4)
+                        .map(|_| {
+                            let q = Arc::clone(&queue);
+                            thread::spawn(move || {
+                                let mut local = Vec::new();
+                                while local.len() < 5_000 {
+                                    match q.dequeue() {
+                                        Some(v) => local.push(v),
+                                        None => thread::yield_now(),
+                                    }
+                                }
+                                local
+                            })
+                        })
+                        .collect();
+                    for p in producers {
+                        p.join().unwrap();
+                    }
+                    let mut all = Vec::new();
+                    for c in consumers {
+                        let local = c.join().unwrap();
+                        for p in 0..4 {
+                            let from_p: Vec<_> =
+                                local.iter().filter(|v| **v / 5_000 == p).collect();
+                            assert!(from_p.windows(2).all(|w| w[0] < w[1]));
+                        }
+                        all.extend(local);
+                    }
+                    all.sort_unstable();
+                    all.dedup();
+                    assert_eq!(all.len(), 4 * 5_000);
+                    assert_eq!(queue.dequeue(), None);
+                }
+            }
+        };
+    }
+
+    queue_tests!(non_blocking, Arc::new(NonBlockingQueue::new()));
+
+    #[test]
+    fn drop_frees_pending_items() {
+        let item = Arc::new(());
+        let queue = NonBlockingQueue::new();
+        for _ in 0..10 {
+            queue.enqueue(Arc::clone(&item));
+        }
+        drop(queue.dequeue());
+        drop(queue);
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+}
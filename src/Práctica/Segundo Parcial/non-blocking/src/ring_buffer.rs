@@ -0,0 +1,314 @@
+// Buffer circular acotado MPMC sin locks (Vyukov).
+//
+// Cada casillero tiene un número de secuencia que dice de quién es el turno: si vale `2 * pos`, el
+// casillero está libre para el productor que reserve la posición `pos`; si vale `2 * pos + 1`,
+// tiene un valor listo para el consumidor de esa misma posición. El original usa `pos` y
+// `pos + 1`, pero así un buffer de capacidad 1 no distingue "lleno" de "libre en la vuelta
+// siguiente". Productores y consumidores sólo compiten con un CAS sobre su propio índice (tail o
+// head) y después escriben el casillero sin que nadie más lo toque.
+//
+// Las variantes bloqueantes no hacen spin: el hilo se anota en una cola de espera y se duerme con
+// `thread::park` hasta que alguien del otro lado termine una operación.
+use crate::queue::{NonBlockingQueue, Queue};
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+use std::thread::{self, Thread};
+
+const ACQ: Ordering = Ordering::Acquire;
+const REL: Ordering = Ordering::Release;
+const RELAXED: Ordering = Ordering::Relaxed;
+
+// Head y tail en líneas de caché distintas para que productores y consumidores no se pisen
+#[repr(align(128))]
+pub(crate) struct CachePadded<T>(pub(crate) T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+struct Slot<T> {
+    seq: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+pub struct RingBuffer<T> {
+    slots: Box<[Slot<T>]>,
+    head: CachePadded<AtomicUsize>,
+    tail: CachePadded<AtomicUsize>,
+    // Hilos dormidos esperando lugar (push) o un elemento (pop)
+    push_waiters: NonBlockingQueue<Thread>,
+    pop_waiters: NonBlockingQueue<Thread>,
+}
+
+// Los valores sólo se mueven entre hilos, nunca se comparten
+unsafe impl<T: Send> Send for RingBuffer<T> {}
+unsafe impl<T: Send> Sync for RingBuffer<T> {}
+
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "la capacidad tiene que ser positiva");
+        RingBuffer {
+            slots: (0..capacity)
+                .map(|i| Slot {
+                    seq: AtomicUsize::new(2 * i),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect(),
+            head: CachePadded(AtomicUsize::new(0)),
+            tail: CachePadded(AtomicUsize::new(0)),
+            push_waiters: NonBlockingQueue::new(),
+            pop_waiters: NonBlockingQueue::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    // Aproximado si hay operaciones en curso
+    pub fn len(&self) -> usize {
+        let head = self.head.load(ACQ);
+        let tail = self.tail.load(ACQ);
+        tail.saturating_sub(head).min(self.capacity())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn try_push(&self, value: T) -> Result<(), T> {
+        let result = self.push_once(value);
+        if result.is_ok() {
+            wake_all(&self.pop_waiters);
+        }
+        result
+    }
+
+    pub fn try_pop(&self) -> Option<T> {
+        let result = self.pop_once();
+        if result.is_some() {
+            wake_all(&self.push_waiters);
+        }
+        result
+    }
+
+    // Bloquea mientras el buffer esté lleno
+    pub fn push(&self, mut value: T) {
+        loop {
+            value = match self.try_push(value) {
+                Ok(()) => return,
+                Err(value) => value,
+            };
+            // Nos anotamos antes de reintentar: si el pop que libera lugar ocurre entre el
+            // reintento y el park, ya nos ve en la cola y el unpark deja el permiso guardado
+            self.push_waiters.enqueue(thread::current());
+            atomic::fence(Ordering::SeqCst);
+            value = match self.try_push(value) {
+                Ok(()) => return,
+                Err(value) => value,
+            };
+            thread::park();
+        }
+    }
+
+    // Bloquea mientras el buffer esté vacío
+    pub fn pop(&self) -> T {
+        loop {
+            if let Some(value) = self.try_pop() {
+                return value;
+            }
+            self.pop_waiters.enqueue(thread::current());
+            atomic::fence(Ordering::SeqCst);
+            if let Some(value) = self.try_pop() {
+                return value;
+            }
+            thread::park();
+        }
+    }
+
+    fn push_once(&self, value: T) -> Result<(), T> {
+        let mut pos = self.tail.load(RELAXED);
+        loop {
+            let slot = &self.slots[pos % self.capacity()];
+            let seq = slot.seq.load(ACQ);
+            match seq.wrapping_sub(2 * pos) as isize {
+                0 => match self
+                    .tail
+                    .compare_exchange_weak(pos, pos + 1, RELAXED, RELAXED)
+                {
+                    Ok(_) => {
+                        unsafe { (*slot.value.get()).write(value) };
+                        slot.seq.store(2 * pos + 1, REL);
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                },
+                // El casillero todavía tiene el valor de la vuelta anterior: está lleno
+                diff if diff < 0 => return Err(value),
+                // Otro productor ya reservó esta posición
+                _ => pos = self.tail.load(RELAXED),
+            }
+        }
+    }
+
+    fn pop_once(&self) -> Option<T> {
+        let mut pos = self.head.load(RELAXED);
+        loop {
+            let slot = &self.slots[pos % self.capacity()];
+            let seq = slot.seq.load(ACQ);
+            match seq.wrapping_sub(2 * pos + 1) as isize {
+                0 => match self
+                    .head
+                    .compare_exchange_weak(pos, pos + 1, RELAXED, RELAXED)
+                {
+                    Ok(_) => {
+                        let value = unsafe { (*slot.value.get()).assume_init_read() };
+                        // Libre para el productor de la próxima vuelta
+                        slot.seq.store(2 * (pos + self.capacity()), REL);
+                        return Some(value);
+                    }
+                    Err(current) => pos = current,
+                },
+                // Nadie escribió todavía en esta posición: está vacío
+                diff if diff < 0 => return None,
+                _ => pos = self.head.load(RELAXED),
+            }
+        }
+    }
+}
+
+// Despierta a todos: alguno puede haberse anotado y después terminar por su cuenta, así que
+// despertar sólo a uno podría desperdiciar el aviso
+fn wake_all(waiters: &NonBlockingQueue<Thread>) {
+    atomic::fence(Ordering::SeqCst);
+    while let Some(thread) = waiters.dequeue() {
+        thread.unpark();
+    }
+}
+
+impl<T> Drop for RingBuffer<T> {
+    fn drop(&mut self) {
+        while self.pop_once().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn try_operations_respect_capacity() {
+        let buffer = RingBuffer::new(3);
+        assert_eq!(buffer.try_pop(), None);
+        for i in 0..3 {
+            assert_eq!(buffer.try_push(i), Ok(()));
+        }
+        assert_eq!(buffer.try_push(3), Err(3));
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.try_pop(), Some(0));
+        assert_eq!(buffer.try_push(3), Ok(()));
+        for i in 1..4 {
+            assert_eq!(buffer.try_pop(), Some(i));
+        }
+        assert_eq!(buffer.try_pop(), None);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn wraps_around_many_times() {
+        let buffer = RingBuffer::new(5);
+        for round in 0..1_000 {
+            for i in 0..4 {
+                buffer.try_push(round * 4 + i).unwrap();
+            }
+            for i in 0..4 {
+                assert_eq!(buffer.try_pop(), Some(round * 4 + i));
+            }
+        }
+    }
+
+    #[test]
+    fn push_waits_for_pop() {
+        let buffer = Arc::new(RingBuffer::new(1));
+        buffer.push(1);
+        let pusher = {
+            let b = Arc::clone(&buffer);
+            thread::spawn(move || b.push(2))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!pusher.is_finished());
+        assert_eq!(buffer.pop(), 1);
+        pusher.join().unwrap();
+        assert_eq!(buffer.pop(), 2);
+    }
+
+    #[test]
+    fn pop_waits_for_push() {
+        let buffer = Arc::new(RingBuffer::new(4));
+        let popper = {
+            let b = Arc::clone(&buffer);
+            thread::spawn(move || b.pop())
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!popper.is_finished());
+        buffer.push(7);
+        assert_eq!(popper.join().unwrap(), 7);
+    }
+
+    // Buffer chico para que productores y consumidores se bloqueen seguido
+    #[test]
+    fn blocking_multi_producer_multi_consumer() {
+        let buffer = Arc::new(RingBuffer::new(4));
+        let producers: Vec<_> = (0..4)
+            .map(|p| {
+                let b = Arc::clone(&buffer);
+                thread::spawn(move || {
+                    for i in 0..5_000 {
+                        b.push(p * 5_000 + i);
+                    }
+                })
+            })
+            .collect();
+        let consumers: Vec<_> = (0..4)
+            .map(|_| {
+                let b = Arc::clone(&buffer);
+                thread::spawn(move || (0..5_000).map(|_| b.pop()).collect::<Vec<_>>())
+            })
+            .collect();
+        for p in producers {
+            p.join().unwrap();
+        }
+        let mut all = Vec::new();
+        for c in consumers {
+            let local = c.join().unwrap();
+            for p in 0..4 {
+                let from_p: Vec<_> = local.iter().filter(|v| **v / 5_000 == p).collect();
+                assert!(from_p.windows(2).all(|w| w[0] < w[1]));
+            }
+            all.extend(local);
+        }
+        all.sort_unstable();
+        assert_eq!(all, (0..4 * 5_000).collect::<Vec<_>>());
+        assert_eq!(buffer.try_pop(), None);
+    }
+
+    #[test]
+    fn drop_frees_pending_items() {
+        let item = Arc::new(());
+        let buffer = RingBuffer::new(8);
+        for _ in 0..5 {
+            buffer.push(Arc::clone(&item));
+        }
+        drop(buffer.pop());
+        drop(buffer);
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+}
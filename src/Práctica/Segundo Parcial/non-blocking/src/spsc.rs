@@ -0,0 +1,241 @@
+// Canal acotado de un productor y un consumidor (SPSC) sin locks.
+//
+// Con un solo hilo de cada lado no hace falta ningún CAS: el productor es el único que escribe
+// `tail` y el consumidor el único que escribe `head`, así que alcanza con loads y stores. Cada
+// extremo además se guarda la última posición que vio del otro (`cached_head` / `cached_tail`) y
+// sólo vuelve a leer el índice compartido cuando el valor cacheado dice que el buffer está lleno
+// o vacío: en el caso común no toca la línea de caché del otro hilo.
+//
+// Los errores son los mismos de `std::sync::mpsc`, así se pueden intercambiar.
+use crate::backoff::{Backoff, YieldBackoff};
+use crate::ring_buffer::CachePadded;
+use std::cell::{Cell, UnsafeCell};
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvError, SendError, TryRecvError, TrySendError};
+
+const ACQ: Ordering = Ordering::Acquire;
+const REL: Ordering = Ordering::Release;
+const RELAXED: Ordering = Ordering::Relaxed;
+
+struct Inner<T> {
+    buffer: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    // Posiciones absolutas, el casillero es `pos % capacidad`
+    head: CachePadded<AtomicUsize>,
+    tail: CachePadded<AtomicUsize>,
+    sender_alive: AtomicBool,
+    receiver_alive: AtomicBool,
+}
+
+// Cada casillero lo toca un solo extremo por vez, según head y tail
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Inner<T> {
+    fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn slot(&self, pos: usize) -> *mut MaybeUninit<T> {
+        self.buffer[pos % self.capacity()].get()
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let head = *self.head.0.get_mut();
+        let tail = *self.tail.0.get_mut();
+        for pos in head..tail {
+            unsafe { (*self.slot(pos)).assume_init_drop() };
+        }
+    }
+}
+
+// Sin `Clone` y sin `Sync`: el tipo garantiza que hay un único productor
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+    cached_head: Cell<usize>,
+}
+
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+    cached_tail: Cell<usize>,
+}
+
+unsafe impl<T: Send> Send for Sender<T> {}
+unsafe impl<T: Send> Send for Receiver<T> {}
+
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "la capacidad tiene que ser positiva");
+    let inner = Arc::new(Inner {
+        buffer: (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        head: CachePadded(AtomicUsize::new(0)),
+        tail: CachePadded(AtomicUsize::new(0)),
+        sender_alive: AtomicBool::new(true),
+        receiver_alive: AtomicBool::new(true),
+    });
+    let sender = Sender {
+        inner: Arc::clone(&inner),
+        cached_head: Cell::new(0),
+    };
+    let receiver = Receiver {
+        inner,
+        cached_tail: Cell::new(0),
+    };
+    (sender, receiver)
+}
+
+impl<T> Sender<T> {
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let inner = &*self.inner;
+        if !inner.receiver_alive.load(ACQ) {
+            return Err(TrySendError::Disconnected(value));
+        }
+        let tail = inner.tail.load(RELAXED);
+        if tail - self.cached_head.get() == inner.capacity() {
+            self.cached_head.set(inner.head.load(ACQ));
+            if tail - self.cached_head.get() == inner.capacity() {
+                return Err(TrySendError::Full(value));
+            }
+        }
+        unsafe { (*inner.slot(tail)).write(value) };
+        inner.tail.store(tail + 1, REL);
+        Ok(())
+    }
+
+    // Espera mientras el buffer esté lleno; falla sólo si el receptor ya no existe
+    pub fn send(&self, mut value: T) -> Result<(), SendError<T>> {
+        let mut backoff = YieldBackoff;
+        loop {
+            value = match self.try_send(value) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Disconnected(value)) => return Err(SendError(value)),
+                Err(TrySendError::Full(value)) => value,
+            };
+            backoff.backoff();
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.inner.sender_alive.store(false, REL);
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let inner = &*self.inner;
+        let head = inner.head.load(RELAXED);
+        if head == self.cached_tail.get() {
+            // Leemos el flag antes que el tail: si el emisor ya se fue, todo lo que mandó está
+            // publicado y un buffer vacío quiere decir que no llega nada más
+            let disconnected = !inner.sender_alive.load(ACQ);
+            self.cached_tail.set(inner.tail.load(ACQ));
+            if head == self.cached_tail.get() {
+                return Err(if disconnected {
+                    TryRecvError::Disconnected
+                } else {
+                    TryRecvError::Empty
+                });
+            }
+        }
+        let value = unsafe { (*inner.slot(head)).assume_init_read() };
+        inner.head.store(head + 1, REL);
+        Ok(value)
+    }
+
+    // Espera mientras el buffer esté vacío; falla cuando no hay más datos y el emisor se fue
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut backoff = YieldBackoff;
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+                Err(TryRecvError::Empty) => backoff.backoff(),
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(|| self.recv().ok())
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.inner.receiver_alive.store(false, REL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn try_operations_respect_capacity() {
+        let (tx, rx) = channel(2);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.try_send(1).unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(rx.try_recv(), Ok(1));
+        tx.try_send(3).unwrap();
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn receiver_drains_before_reporting_disconnect() {
+        let (tx, rx) = channel(4);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn send_fails_once_receiver_is_gone() {
+        let (tx, rx) = channel(1);
+        tx.send(1).unwrap();
+        drop(rx);
+        // Aunque el buffer esté lleno, no se queda esperando
+        assert_eq!(tx.send(2), Err(SendError(2)));
+        assert_eq!(tx.try_send(3), Err(TrySendError::Disconnected(3)));
+    }
+
+    // Buffer chico para que los dos lados tengan que esperarse seguido
+    #[test]
+    fn delivers_everything_in_order_across_threads() {
+        let (tx, rx) = channel(8);
+        let producer = thread::spawn(move || {
+            for i in 0..100_000 {
+                tx.send(i).unwrap();
+            }
+        });
+        let received: Vec<_> = rx.iter().collect();
+        producer.join().unwrap();
+        assert_eq!(received, (0..100_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn drop_frees_pending_items() {
+        let item = Arc::new(());
+        let (tx, rx) = channel(8);
+        for _ in 0..5 {
+            tx.send(Arc::clone(&item)).unwrap();
+        }
+        drop(rx.recv());
+        drop(tx);
+        drop(rx);
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+}
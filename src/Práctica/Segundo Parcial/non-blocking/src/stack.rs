@@ -1,132 +1,352 @@
-// use std::collections::VecDeque;
-use std::ptr::null_mut;
+// NonBlockingStack versiona su head con un TaggedPtr, que necesita punteros de 64 bits: en
+// otras plataformas sólo queda BlockingStack.
+#[cfg(target_pointer_width = "64")]
+use crate::hazard::{HazardDomain, HazardGuard};
+#[cfg(target_pointer_width = "64")]
+use crate::tagged::{AtomicTaggedPtr, TaggedPtr};
+use std::collections::VecDeque;
+#[cfg(target_pointer_width = "64")]
+use std::marker::PhantomData;
+#[cfg(target_pointer_width = "64")]
+use std::mem::ManuallyDrop;
+#[cfg(target_pointer_width = "64")]
+use std::ptr::{self, null_mut};
+#[cfg(target_pointer_width = "64")]
 use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 use std::sync::{Condvar, Mutex};
-use std::thread;
 use std::time::Duration;
 
-trait Stack<T> {
+pub trait Stack<T> {
     fn push(&self, value: T);
     fn pop(&self) -> Option<T>;
+
+    // Como pop, pero nunca se queda esperando a que haya elementos
+    fn try_pop(&self) -> Option<T> {
+        self.pop()
+    }
 }
 
+#[cfg(target_pointer_width = "64")]
 const ACQ: Ordering = Ordering::Acquire;
+#[cfg(target_pointer_width = "64")]
 const REL: Ordering = Ordering::Release;
 
-// struct BlockingStack<T> {
-//     elements: Mutex<VecDeque<T>>,
-//     not_empty: Condvar,
-//     not_full: Condvar,
-//     size: AtomicUsize,
-//     capacity: usize,
-// }
-
-// impl<T> BlockingStack<T> {
-//     pub fn new(capacity: usize) -> Self {
-//         let vec = VecDeque::with_capacity(capacity);
-//         let elements = Mutex::new(vec);
-//         BlockingStack {
-//             elements,
-//             not_empty: Condvar::new(),
-//             not_full: Condvar::new(),
-//             size: AtomicUsize::new(0),
-//             capacity,
-//         }
-//     }
-// }
-//
-// impl<T> Stack<T> for BlockingStack<T> {
-//     fn push(&self, value: T) {
-//         let mut elements = self.elements.lock().unwrap();
-//         while self.size.load(Ordering::Relaxed) >= self.capacity {
-//             elements = self.not_full.wait(elements).unwrap();
-//         }
-//         elements.push_front(value);
-//         self.size.fetch_add(1, Ordering::Relaxed);
-//         self.not_empty.notify_one();
-//     }
-//
-//     fn pop(&self) -> Option<T> {
-//         let mut elements = self.elements.lock().unwrap();
-//         while self.size.load(Ordering::Relaxed) == 0 {
-//             elements = self.not_empty.wait(elements).unwrap();
-//         }
-//         let item = elements.pop_front();
-//         self.size.fetch_sub(1, Ordering::Relaxed);
-//         self.not_full.notify_one();
-//         item
-//     }
-// }
-
-struct NonBlockingStack<T> {
-    head: AtomicPtr<Node<T>>,
+pub struct BlockingStack<T> {
+    elements: Mutex<VecDeque<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+}
+
+impl<T> BlockingStack<T> {
+    pub fn new(capacity: usize) -> Self {
+        let vec = VecDeque::with_capacity(capacity);
+        let elements = Mutex::new(vec);
+        BlockingStack {
+            elements,
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Si está llena devuelve el valor en lugar de esperar
+    pub fn try_push(&self, value: T) -> Result<(), T> {
+        let mut elements = self.elements.lock().unwrap();
+        if elements.len() >= self.capacity {
+            return Err(value);
+        }
+        elements.push_front(value);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    pub fn push_timeout(&self, value: T, timeout: Duration) -> Result<(), T> {
+        let (mut elements, result) = self
+            .not_full
+            .wait_timeout_while(self.elements.lock().unwrap(), timeout, |e| {
+                e.len() >= self.capacity
+            })
+            .unwrap();
+        if result.timed_out() {
+            return Err(value);
+        }
+        elements.push_front(value);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let (mut elements, _) = self
+            .not_empty
+            .wait_timeout_while(self.elements.lock().unwrap(), timeout, |e| e.is_empty())
+            .unwrap();
+        let item = elements.pop_front();
+        if item.is_some() {
+            self.not_full.notify_one();
+        }
+        item
+    }
+}
+
+// push espera mientras esté llena y pop mientras esté vacía, así que pop nunca devuelve None
+impl<T> Stack<T> for BlockingStack<T> {
+    fn push(&self, value: T) {
+        let mut elements = self
+            .not_full
+            .wait_while(self.elements.lock().unwrap(), |e| e.len() >= self.capacity)
+            .unwrap();
+        elements.push_front(value);
+        self.not_empty.notify_one();
+    }
+
+    fn pop(&self) -> Option<T> {
+        let mut elements = self
+            .not_empty
+            .wait_while(self.elements.lock().unwrap(), |e| e.is_empty())
+            .unwrap();
+        let item = elements.pop_front();
+        self.not_full.notify_one();
+        item
+    }
+
+    fn try_pop(&self) -> Option<T> {
+        let item = self.elements.lock().unwrap().pop_front();
+        if item.is_some() {
+            self.not_full.notify_one();
+        }
+        item
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+pub struct NonBlockingStack<T> {
+    // Cada CAS exitoso avanza la generación del head, así un pop demorado no confunde un nodo
+    // que salió y volvió a entrar con el que había leído
+    head: AtomicTaggedPtr<Node<T>>,
     size: AtomicUsize,
+    domain: HazardDomain,
+    _marker: PhantomData<T>,
 }
 
+// Sin esto el head haría a la pila Send + Sync para cualquier T. push y pop sólo mueven los
+// elementos de un hilo a otro, pero `iter` los lee desde varios hilos a la vez.
+#[cfg(target_pointer_width = "64")]
+unsafe impl<T: Send> Send for NonBlockingStack<T> {}
+#[cfg(target_pointer_width = "64")]
+unsafe impl<T: Send + Sync> Sync for NonBlockingStack<T> {}
+
+#[cfg(target_pointer_width = "64")]
 impl<T> NonBlockingStack<T> {
+    // La pila vacía es un head nulo: no hay nodo dummy
     pub fn new() -> Self {
-        let dummy = Box::into_raw(Box::new(Node::dummy()));
         NonBlockingStack {
-            head: AtomicPtr::new(dummy),
+            head: AtomicTaggedPtr::new(TaggedPtr::null()),
             size: AtomicUsize::new(0),
+            domain: HazardDomain::new(),
+            _marker: PhantomData,
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.size.load(ACQ)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(ACQ).is_null()
+    }
+
+    /// Copia de los elementos de arriba hacia abajo. Si la pila cambia durante el recorrido se
+    /// vuelve a empezar, así que lo devuelto es un estado en el que la pila realmente estuvo.
+    ///
+    /// Sólo existe para `T: Copy` y no se puede extender a tipos con memoria propia como
+    /// `String`. Un pop concurrente se lleva el item copiando sus bits y desde ese momento lo puede
+    /// liberar: el hazard mantiene vivo el nodo, pero no lo que el item tiene adentro. Clonarlo o
+    /// devolver una referencia al nodo sería un use-after-free. Para esos tipos, `pop` hasta
+    /// vaciarla o usar `BlockingStack`.
+    ///
+    /// ```compile_fail
+    /// use non_blocking::stack::{NonBlockingStack, Stack};
+    /// let stack = NonBlockingStack::new();
+    /// stack.push(String::from("a"));
+    /// stack.iter();
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = T>
+    where
+        T: Copy,
+    {
+        let cur_guard = self.domain.acquire();
+        let next_guard = self.domain.acquire();
+        'retry: loop {
+            let head = self.protect_head(&cur_guard);
+            let mut items = Vec::new();
+            let mut cur = head.ptr();
+            while !cur.is_null() {
+                items.push(unsafe { *(*cur).item });
+                let next = unsafe { (*cur).next.load(ACQ) };
+                next_guard.set(next);
+                // Mismo head y misma generación: nadie sacó nada, next sigue vivo
+                if self.head.load(ACQ) != head {
+                    continue 'retry;
+                }
+                cur_guard.set(next);
+                cur = next;
+            }
+            return items.into_iter();
+        }
+    }
+
+    // Igual que HazardGuard::protect, pero comparando también la generación
+    fn protect_head(&self, guard: &HazardGuard) -> TaggedPtr<Node<T>> {
+        let mut head = self.head.load(ACQ);
+        loop {
+            guard.set(head.ptr());
+            let current = self.head.load(ACQ);
+            if current == head {
+                return head;
+            }
+            head = current;
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<T> Default for NonBlockingStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
+#[cfg(target_pointer_width = "64")]
 impl<T> Stack<T> for NonBlockingStack<T> {
     fn push(&self, value: T) {
-        let new_node = Box::into_raw(Box::new(Node::new(value)));
-        loop {
-            let head = self.head.load(ACQ);
-            unsafe { (*new_node).next.store(head, Ordering::Relaxed) };
-            if self
-                .head
-                .compare_exchange(head, new_node, Ordering::Release, ACQ)
-                .is_ok()
-            {
-                self.size.fetch_add(1, REL);
-                break;
-            }
+        let mut node = Box::new(Node::new(value));
+        while let Err(n) = self.try_push_node(node) {
+            node = n;
         }
     }
 
     fn pop(&self) -> Option<T> {
+        let guard = self.domain.acquire();
         loop {
-            let cur_head = self.head.load(ACQ);
-            if cur_head.is_null() {
-                return None;
+            if let Ok(item) = self.try_pop_with(&guard) {
+                return item;
             }
-            let next_node = unsafe { (*cur_head).next.load(ACQ) };
-            if self
-                .head
-                .compare_exchange(cur_head, next_node, Ordering::AcqRel, ACQ)
-                .is_ok()
-            {
+        }
+    }
+}
+
+// Un solo intento de CAS sobre el head. EliminationBackoffStack los usa para probar suerte en el
+// arreglo de eliminación entre intento e intento.
+#[cfg(target_pointer_width = "64")]
+impl<T> NonBlockingStack<T> {
+    pub(crate) fn try_push_node(&self, node: Box<Node<T>>) -> Result<(), Box<Node<T>>> {
+        let head = self.head.load(ACQ);
+        node.next.store(head.ptr(), Ordering::Relaxed);
+        let new_node = Box::into_raw(node);
+        // Se cuenta antes de publicar el nodo: si no, un pop que ya lo ve podría restar primero y
+        // el contador daría la vuelta. Si el CAS falla, se descuenta.
+        self.size.fetch_add(1, REL);
+        match self
+            .head
+            .compare_exchange(head, head.next(new_node), Ordering::Release, ACQ)
+        {
+            Ok(_) => Ok(()),
+            Err(_) => {
                 self.size.fetch_sub(1, REL);
-                let old_head_node = unsafe { Box::from_raw(cur_head) };
-                return old_head_node.item;
+                Err(unsafe { Box::from_raw(new_node) })
             }
         }
     }
+
+    // Err si otro hilo ganó el CAS; Ok(None) si la pila estaba vacía
+    pub(crate) fn try_pop_once(&self) -> Result<Option<T>, ()> {
+        let guard = self.domain.acquire();
+        self.try_pop_with(&guard)
+    }
+
+    fn try_pop_with(&self, guard: &HazardGuard) -> Result<Option<T>, ()> {
+        match self.read_top(guard) {
+            None => Ok(None),
+            Some((cur_head, next_node)) => self.try_unlink(guard, cur_head, next_node).map(Some),
+        }
+    }
+
+    // El pop en dos pasos, para que los tests puedan meter otras operaciones en el medio
+    fn read_top(&self, guard: &HazardGuard) -> Option<(TaggedPtr<Node<T>>, *mut Node<T>)> {
+        // Sin el hazard, otro pop podría liberar cur_head antes de leer su next
+        let cur_head = self.protect_head(guard);
+        if cur_head.is_null() {
+            return None;
+        }
+        Some((cur_head, unsafe { (*cur_head.ptr()).next.load(ACQ) }))
+    }
+
+    fn try_unlink(
+        &self,
+        guard: &HazardGuard,
+        cur_head: TaggedPtr<Node<T>>,
+        next_node: *mut Node<T>,
+    ) -> Result<T, ()> {
+        if self
+            .head
+            .compare_exchange(cur_head, cur_head.next(next_node), Ordering::AcqRel, ACQ)
+            .is_err()
+        {
+            return Err(());
+        }
+        self.size.fetch_sub(1, REL);
+        guard.clear();
+        // Ganamos el CAS: el item es nuestro. Lo copiamos sin escribir el nodo porque otros
+        // hilos todavía pueden estar leyéndolo; liberar el nodo no vuelve a dropear el item.
+        let item = unsafe { ptr::read(&(*cur_head.ptr()).item) };
+        unsafe { self.domain.retire(cur_head.ptr()) };
+        Ok(ManuallyDrop::into_inner(item))
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<T> Drop for NonBlockingStack<T> {
+    fn drop(&mut self) {
+        let mut cur = self.head.get_mut().ptr();
+        while !cur.is_null() {
+            let node = unsafe { Box::from_raw(cur) };
+            cur = node.next.load(Ordering::Relaxed);
+            drop(node.into_item());
+        }
+    }
 }
 
-struct Node<T> {
-    item: Option<T>,
+#[cfg(target_pointer_width = "64")]
+pub(crate) struct Node<T> {
+    // El pop saca el item con ptr::read, así que el nodo nunca lo dropea por su cuenta
+    item: ManuallyDrop<T>,
     next: AtomicPtr<Node<T>>,
 }
 
+#[cfg(target_pointer_width = "64")]
 impl<T> Node<T> {
-    fn dummy() -> Self {
+    pub(crate) fn new(item: T) -> Self {
         Node {
-            item: None,
+            item: ManuallyDrop::new(item),
             next: AtomicPtr::new(null_mut()),
         }
     }
-    fn new(item: T) -> Self {
-        Node {
-            item: Some(item),
-            next: AtomicPtr::new(null_mut()),
-        }
+
+    pub(crate) fn into_item(self) -> T {
+        ManuallyDrop::into_inner(self.item)
     }
 }
 
@@ -134,6 +354,8 @@ impl<T> Node<T> {
 mod tests {
     use super::*;
     use std::sync::Arc;
+    use std::thread;
+    use std::time::Instant;
 
     macro_rules! stack_tests {
         ($mod_name:ident, $stack_expr:expr) => {
@@ -142,12 +364,12 @@ mod tests {
                 #[test]
                 fn single_thread_push_pop_simple() {
                     let stack = $stack_expr;
-                    assert_eq!(stack.pop(), None);
+                    assert_eq!(stack.try_pop(), None);
                     stack.push(10);
                     stack.push(20);
                     assert_eq!(stack.pop(), Some(20));
                     assert_eq!(stack.pop(), Some(10));
-                    assert_eq!(stack.pop(), None);
+                    assert_eq!(stack.try_pop(), None);
                 }
 
                 #[test]
@@ -174,7 +396,7 @@ mod tests {
                     for th in threads {
                         th.join().unwrap();
                     }
-                    assert_eq!(stack.pop(), None);
+                    assert_eq!(stack.try_pop(), None);
                     let vals = results.lock().unwrap();
                     assert_eq!(vals.len(), 8 * 1000);
                     let mut uniq = vals.clone();
@@ -201,12 +423,303 @@ mod tests {
                     for h in handles {
                         h.join().unwrap();
                     }
-                    assert_eq!(stack.pop(), None);
+                    assert_eq!(stack.try_pop(), None);
+                }
+
+                // Muchos pops chicos intercalados: con el Box::from_raw inmediato, un pop
+                // podía leer el next de un nodo ya liberado (se ve con Miri o ASan)
+                #[test]
+                fn concurrent_pops_never_touch_freed_nodes() {
+                    let stack = $stack_expr;
+                    let rounds = if cfg!(miri) { 20 } else { 2_000 };
+                    let handles: Vec<_> = (0..6)
+                        .map(|t| {
+                            let s = Arc::clone(&stack);
+                            thread::spawn(move || {
+                                let mut popped = 0;
+                                for i in 0..rounds {
+                                    s.push(t * rounds + i);
+                                    s.push(t * rounds + i);
+                                    popped += s.pop().is_some() as usize;
+                                    popped += s.pop().is_some() as usize;
+                                }
+                                popped
+                            })
+                        })
+                        .collect();
+                    let mut popped: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+                    while stack.try_pop().is_some() {
+                        popped += 1;
+                    }
+                    assert_eq!(popped, 6 * 2 * rounds as usize);
+                    assert_eq!(stack.try_pop(), None);
                 }
             }
         };
     }
 
+    #[cfg(target_pointer_width = "64")]
     stack_tests!(non_blocking, Arc::new(NonBlockingStack::new()));
-    // stack_tests!(blocking, Arc::new(BlockingStack::new(1000)));
+    stack_tests!(blocking, Arc::new(BlockingStack::new(8 * 1000)));
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn non_blocking_empty_stack_stays_empty() {
+        let stack = NonBlockingStack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.pop(), None);
+        stack.push(1);
+        assert!(!stack.is_empty());
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+        assert!(stack.is_empty());
+        assert_eq!(stack.len(), 0);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn non_blocking_len_tracks_pushes_and_pops() {
+        let stack = Arc::new(NonBlockingStack::new());
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let s = Arc::clone(&stack);
+                thread::spawn(move || {
+                    for i in 0..1_000 {
+                        s.push(t * 1_000 + i);
+                    }
+                    for _ in 0..250 {
+                        s.pop().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(stack.len(), 3_000);
+        assert_eq!(stack.iter().count(), 3_000);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn non_blocking_drop_frees_every_node() {
+        let item = Arc::new(());
+        let stack = NonBlockingStack::new();
+        for _ in 0..100 {
+            stack.push(Arc::clone(&item));
+        }
+        for _ in 0..10 {
+            drop(stack.pop());
+        }
+        drop(stack);
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn non_blocking_iter_is_a_top_to_bottom_snapshot() {
+        let stack = NonBlockingStack::new();
+        assert_eq!(stack.iter().next(), None);
+        for i in 0..5 {
+            stack.push(i);
+        }
+        let snapshot: Vec<_> = stack.iter().collect();
+        assert_eq!(snapshot, vec![4, 3, 2, 1, 0]);
+        stack.push(5);
+        assert_eq!(stack.len(), 6);
+        assert_eq!(stack.pop(), Some(5));
+        assert_eq!(stack.iter().collect::<Vec<_>>(), snapshot);
+    }
+
+    // Los elementos de abajo nunca se tocan: cualquier snapshot tiene que terminar con ellos
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn non_blocking_iter_under_concurrent_updates() {
+        let stack = Arc::new(NonBlockingStack::new());
+        for i in 0..100 {
+            stack.push(i);
+        }
+        let base: Vec<_> = (0..100).rev().collect();
+        let writers: Vec<_> = (0..3)
+            .map(|t| {
+                let s = Arc::clone(&stack);
+                thread::spawn(move || {
+                    for i in 0..2_000 {
+                        s.push(1_000 + t * 2_000 + i);
+                        s.pop().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..200 {
+            let snapshot: Vec<_> = stack.iter().collect();
+            assert!(snapshot.len() >= 100);
+            assert_eq!(snapshot[snapshot.len() - 100..], base[..]);
+        }
+        for w in writers {
+            w.join().unwrap();
+        }
+        assert_eq!(stack.iter().collect::<Vec<_>>(), base);
+    }
+
+    // La NonBlockingStack de antes: un AtomicPtr sin generación como head y sin hazards. pop no
+    // libera el nodo y push puede volver a meter uno ya usado, que es lo que pasa cuando el
+    // allocator le da al push la dirección que un pop acaba de liberar: así el ABA pasa siempre.
+    #[cfg(target_pointer_width = "64")]
+    struct UntaggedStack {
+        head: AtomicPtr<Node<i32>>,
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    impl UntaggedStack {
+        fn push_node(&self, node: *mut Node<i32>) {
+            loop {
+                let head = self.head.load(ACQ);
+                unsafe { (*node).next.store(head, Ordering::Relaxed) };
+                if self
+                    .head
+                    .compare_exchange(head, node, Ordering::Release, ACQ)
+                    .is_ok()
+                {
+                    return;
+                }
+            }
+        }
+
+        fn read_top(&self) -> Option<(*mut Node<i32>, *mut Node<i32>)> {
+            let cur_head = self.head.load(ACQ);
+            if cur_head.is_null() {
+                return None;
+            }
+            Some((cur_head, unsafe { (*cur_head).next.load(ACQ) }))
+        }
+
+        fn try_unlink(&self, cur_head: *mut Node<i32>, next_node: *mut Node<i32>) -> bool {
+            self.head
+                .compare_exchange(cur_head, next_node, Ordering::AcqRel, ACQ)
+                .is_ok()
+        }
+
+        fn pop_node(&self) -> *mut Node<i32> {
+            loop {
+                let (cur_head, next_node) = self.read_top().unwrap();
+                if self.try_unlink(cur_head, next_node) {
+                    return cur_head;
+                }
+            }
+        }
+    }
+
+    // Pila a -> b -> c. El hilo 1 lee head = a y next = b y se queda dormido. Mientras tanto el
+    // hilo 2 saca a, saca b y vuelve a meter a: la pila queda a -> c. Cuando el hilo 1 despierta,
+    // su CAS(a, b) sólo compara la dirección, tiene éxito y deja como head a b, que ya no está en
+    // la pila.
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn untagged_stack_suffers_aba() {
+        let stack = UntaggedStack {
+            head: AtomicPtr::new(null_mut()),
+        };
+        let [a, b, c] = [1, 2, 3].map(|v| Box::into_raw(Box::new(Node::new(v))));
+        for node in [c, b, a] {
+            stack.push_node(node);
+        }
+
+        // Hilo 1
+        let (seen_head, seen_next) = stack.read_top().unwrap();
+
+        // Hilo 2
+        assert_eq!(stack.pop_node(), a);
+        assert_eq!(stack.pop_node(), b);
+        stack.push_node(a);
+
+        // Hilo 1
+        assert!(stack.try_unlink(seen_head, seen_next));
+        assert_eq!(stack.head.load(ACQ), b);
+        for node in [a, b, c] {
+            drop(unsafe { Box::from_raw(node) });
+        }
+    }
+
+    // La misma secuencia sobre NonBlockingStack: el hazard del hilo 1 no deja que se libere a, y
+    // aunque la dirección volviera al head la generación sería otra. El CAS falla y el reintento
+    // ve la pila como quedó.
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn non_blocking_stack_is_not_fooled_by_aba() {
+        let stack = NonBlockingStack::new();
+        for i in [3, 2, 1] {
+            stack.push(i);
+        }
+
+        // Hilo 1
+        let guard = stack.domain.acquire();
+        let (seen_head, seen_next) = stack.read_top(&guard).unwrap();
+
+        // Hilo 2
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), Some(2));
+        stack.push(1);
+
+        // Hilo 1
+        assert_eq!(stack.try_unlink(&guard, seen_head, seen_next), Err(()));
+        assert_eq!(stack.try_pop_with(&guard), Ok(Some(1)));
+        drop(guard);
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn blocking_try_push_fails_when_full() {
+        let stack = BlockingStack::new(2);
+        assert_eq!(stack.try_push(1), Ok(()));
+        assert_eq!(stack.try_push(2), Ok(()));
+        assert_eq!(stack.try_push(3), Err(3));
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.try_pop(), Some(2));
+        assert_eq!(stack.try_push(3), Ok(()));
+    }
+
+    #[test]
+    fn blocking_timed_operations_give_up() {
+        let stack = BlockingStack::new(1);
+        let t0 = Instant::now();
+        assert_eq!(stack.pop_timeout(Duration::from_millis(50)), None);
+        assert!(t0.elapsed() >= Duration::from_millis(50));
+
+        stack.push(1);
+        let t1 = Instant::now();
+        assert_eq!(stack.push_timeout(2, Duration::from_millis(50)), Err(2));
+        assert!(t1.elapsed() >= Duration::from_millis(50));
+        assert_eq!(stack.pop_timeout(Duration::from_millis(50)), Some(1));
+    }
+
+    #[test]
+    fn blocking_push_waits_for_pop() {
+        let stack = Arc::new(BlockingStack::new(1));
+        stack.push(1);
+        let pusher = {
+            let s = Arc::clone(&stack);
+            thread::spawn(move || s.push(2))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!pusher.is_finished());
+        assert_eq!(stack.pop(), Some(1));
+        pusher.join().unwrap();
+        assert_eq!(stack.pop_timeout(Duration::from_secs(1)), Some(2));
+    }
+
+    #[test]
+    fn blocking_pop_waits_for_push() {
+        let stack = Arc::new(BlockingStack::new(1));
+        let popper = {
+            let s = Arc::clone(&stack);
+            thread::spawn(move || s.pop())
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!popper.is_finished());
+        stack.push(7);
+        assert_eq!(popper.join().unwrap(), Some(7));
+    }
 }
@@ -0,0 +1,207 @@
+// Puntero con "stamp" para evitar el problema ABA.
+//
+// Un CAS sobre un AtomicPtr sólo compara direcciones: si entre la lectura y el CAS alguien saca
+// el nodo A, saca B y vuelve a meter A (o un nodo nuevo que el allocator puso en la misma
+// dirección), el CAS tiene éxito aunque la estructura cambió. Acá cada escritura incrementa un
+// contador de generación que viaja en el mismo usize que el puntero, así que ese CAS falla.
+//
+// En x86_64 y aarch64 las direcciones de usuario entran en 48 bits, así que los 16 bits altos
+// quedan libres para el tag. Por eso el módulo sólo existe con punteros de 64 bits, y `new`
+// verifica la dirección también en release: con direcciones de 57 bits (LA57) un tag pisaría el
+// puntero sin que nadie se entere.
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+const ADDR_BITS: u32 = 48;
+const ADDR_MASK: usize = (1 << ADDR_BITS) - 1;
+
+pub struct TaggedPtr<T> {
+    raw: usize,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T> TaggedPtr<T> {
+    pub fn new(ptr: *mut T, tag: u16) -> Self {
+        let addr = ptr.expose_provenance();
+        assert_eq!(addr & !ADDR_MASK, 0, "la dirección no entra en 48 bits");
+        TaggedPtr {
+            raw: addr | (tag as usize) << ADDR_BITS,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn null() -> Self {
+        Self::new(std::ptr::null_mut(), 0)
+    }
+
+    pub fn ptr(self) -> *mut T {
+        std::ptr::with_exposed_provenance_mut(self.raw & ADDR_MASK)
+    }
+
+    pub fn tag(self) -> u16 {
+        (self.raw >> ADDR_BITS) as u16
+    }
+
+    pub fn is_null(self) -> bool {
+        self.ptr().is_null()
+    }
+
+    // El valor que hay que escribir para reemplazar a `self`: otro puntero, la generación siguiente
+    pub fn next(self, ptr: *mut T) -> Self {
+        Self::new(ptr, self.tag().wrapping_add(1))
+    }
+
+    fn from_raw(raw: usize) -> Self {
+        TaggedPtr {
+            raw,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for TaggedPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TaggedPtr<T> {}
+
+impl<T> PartialEq for TaggedPtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T> Eq for TaggedPtr<T> {}
+
+impl<T> fmt::Debug for TaggedPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedPtr")
+            .field("ptr", &self.ptr())
+            .field("tag", &self.tag())
+            .finish()
+    }
+}
+
+pub struct AtomicTaggedPtr<T> {
+    raw: AtomicUsize,
+    // Mismas garantías de Send/Sync que un AtomicPtr<T>
+    _marker: PhantomData<AtomicPtr<T>>,
+}
+
+impl<T> AtomicTaggedPtr<T> {
+    pub fn new(value: TaggedPtr<T>) -> Self {
+        AtomicTaggedPtr {
+            raw: AtomicUsize::new(value.raw),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn load(&self, order: Ordering) -> TaggedPtr<T> {
+        TaggedPtr::from_raw(self.raw.load(order))
+    }
+
+    pub fn store(&self, value: TaggedPtr<T>, order: Ordering) {
+        self.raw.store(value.raw, order);
+    }
+
+    pub fn compare_exchange(
+        &self,
+        current: TaggedPtr<T>,
+        new: TaggedPtr<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedPtr<T>, TaggedPtr<T>> {
+        self.raw
+            .compare_exchange(current.raw, new.raw, success, failure)
+            .map(TaggedPtr::from_raw)
+            .map_err(TaggedPtr::from_raw)
+    }
+
+    pub fn get_mut(&mut self) -> TaggedPtr<T> {
+        TaggedPtr::from_raw(*self.raw.get_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr::null_mut;
+
+    const SEQ: Ordering = Ordering::SeqCst;
+
+    struct Node {
+        value: i32,
+        next: AtomicPtr<Node>,
+    }
+
+    fn node(value: i32, next: *mut Node) -> *mut Node {
+        Box::into_raw(Box::new(Node {
+            value,
+            next: AtomicPtr::new(next),
+        }))
+    }
+
+    fn free(nodes: [*mut Node; 3]) {
+        for n in nodes {
+            drop(unsafe { Box::from_raw(n) });
+        }
+    }
+
+    #[test]
+    fn packs_pointer_and_tag() {
+        let mut x = 5;
+        let p = TaggedPtr::new(&mut x as *mut i32, 7);
+        assert_eq!(p.ptr(), &mut x as *mut i32);
+        assert_eq!(p.tag(), 7);
+        assert_eq!(unsafe { *p.ptr() }, 5);
+        let q = p.next(null_mut());
+        assert!(q.is_null());
+        assert_eq!(q.tag(), 8);
+        assert_eq!(
+            TaggedPtr::new(null_mut::<i32>(), u16::MAX)
+                .next(null_mut())
+                .tag(),
+            0
+        );
+    }
+
+    // Con una dirección de 57 bits el tag pisaría el puntero: falla también en release
+    #[test]
+    #[should_panic(expected = "la dirección no entra en 48 bits")]
+    fn rejects_addresses_wider_than_48_bits() {
+        TaggedPtr::new(std::ptr::without_provenance_mut::<i32>(1 << 56), 0);
+    }
+
+    // Un CAS sobre la generación vieja falla aunque la dirección haya vuelto a ser la misma.
+    // La secuencia completa, sobre las pilas, está en los tests de stack.rs.
+    #[test]
+    fn tagged_pointer_cas_detects_aba() {
+        let c = node(3, null_mut());
+        let b = node(2, c);
+        let a = node(1, b);
+        let head = AtomicTaggedPtr::new(TaggedPtr::new(a, 0));
+
+        // Hilo 1
+        let seen_head = head.load(SEQ);
+        let seen_next = unsafe { (*seen_head.ptr()).next.load(SEQ) };
+
+        // Hilo 2: cada escritura avanza la generación
+        let h = head.load(SEQ);
+        head.compare_exchange(h, h.next(b), SEQ, SEQ).unwrap();
+        let h = head.load(SEQ);
+        head.compare_exchange(h, h.next(c), SEQ, SEQ).unwrap();
+        unsafe { (*a).next.store(c, SEQ) };
+        let h = head.load(SEQ);
+        head.compare_exchange(h, h.next(a), SEQ, SEQ).unwrap();
+
+        // Hilo 1: la dirección coincide pero la generación no
+        assert_eq!(head.load(SEQ).ptr(), seen_head.ptr());
+        let result = head.compare_exchange(seen_head, seen_head.next(seen_next), SEQ, SEQ);
+        assert_eq!(result, Err(TaggedPtr::new(a, 3)));
+        assert_eq!(unsafe { (*head.load(SEQ).ptr()).value }, 1);
+        free([a, b, c]);
+    }
+}